@@ -1,4 +1,4 @@
-use crate::token::{Token, TokenType};
+use crate::token::{Span, Token, TokenType};
 use std::fmt::Display;
 
 #[derive(PartialEq, Debug)]
@@ -12,9 +12,67 @@ pub enum Node {
 
 #[derive(PartialEq, Debug, Clone, PartialOrd)]
 pub enum Statement {
-    Let { name: String, value: Expression },
-    Return { value: Expression },
-    StatmentExpression { value: Expression },
+    Let {
+        name: String,
+        value: Expression,
+        span: Span,
+    },
+    /// Like `Let` but the binding can not be assigned to afterwards
+    Const {
+        name: String,
+        value: Expression,
+        span: Span,
+    },
+    Return {
+        value: Expression,
+        span: Span,
+    },
+    StatmentExpression {
+        value: Expression,
+        span: Span,
+    },
+    While {
+        condition: Expression,
+        body: BlockStatment,
+        span: Span,
+    },
+    /// `for variable in iterable { body }`
+    For {
+        variable: String,
+        iterable: Expression,
+        body: BlockStatment,
+        span: Span,
+    },
+    Break {
+        span: Span,
+    },
+    Continue {
+        span: Span,
+    },
+    /// `fn name(parameters) { body }`, `value` is the function literal. Declarations are hoisted
+    /// to the start of the block they are in
+    Function {
+        name: String,
+        value: Expression,
+        span: Span,
+    },
+}
+
+impl Statement {
+    #[allow(dead_code)]
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Let { span, .. } => *span,
+            Statement::Const { span, .. } => *span,
+            Statement::Return { span, .. } => *span,
+            Statement::StatmentExpression { span, .. } => *span,
+            Statement::While { span, .. } => *span,
+            Statement::For { span, .. } => *span,
+            Statement::Break { span } => *span,
+            Statement::Continue { span } => *span,
+            Statement::Function { span, .. } => *span,
+        }
+    }
 }
 
 #[allow(non_snake_case)]
@@ -35,16 +93,17 @@ impl Display for BlockStatment {
     }
 }
 
-#[allow(non_snake_case)]
+#[allow(non_snake_case, clippy::enum_variant_names)]
 #[derive(PartialEq, Debug, Clone, PartialOrd)]
 pub enum Expression {
-    Number(f64),
-    String(String),
-    Indentifier(String),
-    Boolean(bool),
+    Integer(i64, Span),
+    Float(f64, Span),
+    String(String, Span),
+    Indentifier(String, Span),
+    Boolean(bool, Span),
     FunctionLiteral {
         Token: Token,
-        Parameters: Option<Vec<Expression>>,
+        Parameters: Option<Vec<Parameter>>,
         Body: BlockStatment,
     },
     IfExpression {
@@ -66,44 +125,139 @@ pub enum Expression {
         //TODO: I Dont think Option inside box is correct or a good way to handle this
         Right: Box<Option<Expression>>,
     },
+    /// `Function(positional, name: value)`, the named arguments always follow the positional
+    /// ones
     CallExpression {
         Token: Token,
         Function: Box<Expression>,
         Arguments: Option<Vec<Expression>>,
+        NamedArguments: Vec<NamedArgument>,
+    },
+    ArrayLiteral {
+        Token: Token,
+        Elements: Vec<Expression>,
     },
+    IndexExpression {
+        Token: Token,
+        Left: Box<Expression>,
+        Index: Box<Expression>,
+    },
+    HashLiteral {
+        Token: Token,
+        Pairs: Vec<(Expression, Expression)>,
+    },
+    /// `"text ${expression} text"`, text parts are `Expression::String`
+    InterpolatedString {
+        Token: Token,
+        Parts: Vec<Expression>,
+    },
+    /// `Target = Value`, or `Target += Value` and friends where `Op` is the operator applied to
+    /// the current value. The parser only accepts targets that can be assigned to
+    AssignExpression {
+        Token: Token,
+        Target: Box<Expression>,
+        Op: Op,
+        Value: Box<Expression>,
+    },
+}
+
+/// A parameter of a function: `name`, `name = default` or `...name`
+#[derive(PartialEq, Debug, Clone, PartialOrd)]
+pub struct Parameter {
+    pub name: String,
+    /// Evaluated in the scope of the call, after the parameters before it are bound, when the
+    /// call leaves the parameter out
+    pub default: Option<Expression>,
+    /// A rest parameter collects the remaining arguments into an array, only the last parameter
+    /// can be one
+    pub rest: bool,
+    pub span: Span,
+}
+
+impl Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.default, self.rest) {
+            (_, true) => write!(f, "...{}", self.name),
+            (Some(default), false) => write!(f, "{} = {}", self.name, default),
+            (None, false) => write!(f, "{}", self.name),
+        }
+    }
+}
+
+impl Expression {
+    /// Returns where in the source the expression comes from, for composite expressions this is
+    /// the span of the token that introduced it (the operator for infix expressions)
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Integer(_, span)
+            | Expression::Float(_, span)
+            | Expression::String(_, span)
+            | Expression::Indentifier(_, span)
+            | Expression::Boolean(_, span) => *span,
+            Expression::FunctionLiteral { Token, .. }
+            | Expression::IfExpression { Token, .. }
+            | Expression::PrefixExpression { Token, .. }
+            | Expression::InfixExpression { Token, .. }
+            | Expression::CallExpression { Token, .. }
+            | Expression::ArrayLiteral { Token, .. }
+            | Expression::IndexExpression { Token, .. }
+            | Expression::HashLiteral { Token, .. }
+            | Expression::InterpolatedString { Token, .. }
+            | Expression::AssignExpression { Token, .. } => Token.span,
+        }
+    }
 }
 
 #[allow(dead_code)]
 #[derive(PartialEq, PartialOrd, Debug, Clone)]
 pub enum Precedence {
     Lowest,
+    Assign,
+    Or,
+    And,
     Equals,
     LessGreater,
     Sum,
     Product,
     Prefix,
+    Power,
     Call,
+    Index,
 }
 
 impl Precedence {
     pub fn get_precedence(token_type: &TokenType) -> Self {
         match token_type {
+            TokenType::Assign
+            | TokenType::PlusEqual
+            | TokenType::MinusEqual
+            | TokenType::StarEqual
+            | TokenType::SlashEqual => Precedence::Assign,
+            TokenType::Or => Precedence::Or,
+            TokenType::And => Precedence::And,
             TokenType::EqualEqual | TokenType::BangEqual => Precedence::Equals,
             TokenType::Greater | TokenType::Less => Precedence::LessGreater,
             TokenType::Addition | TokenType::Minus => Precedence::Sum,
-            TokenType::Division | TokenType::Star => Precedence::Product,
+            TokenType::Division | TokenType::Star | TokenType::TildeSlash | TokenType::Percent => {
+                Precedence::Product
+            }
+            TokenType::StarStar => Precedence::Power,
             TokenType::LeftParen => Precedence::Call,
+            TokenType::LeftBracket => Precedence::Index,
             _ => Precedence::Lowest,
         }
     }
 }
 
-#[derive(PartialEq, Debug, Clone, PartialOrd)]
+#[derive(PartialEq, Debug, Clone, Copy, PartialOrd)]
 pub enum Op {
     Add,
     Subtract,
     Multiply,
     Divide,
+    FloorDivide,
+    Modulo,
+    Power,
     Bang,
     Equals,
     NotEquals,
@@ -112,16 +266,21 @@ pub enum Op {
     GreaterThan,
     LessThanOrEquals,
     GreaterThanOrEquals,
+    And,
+    Or,
     Call,
 }
 
 impl Op {
     pub fn token(token_type: &TokenType) -> Self {
         match token_type {
-            TokenType::Addition => Self::Add,
-            TokenType::Minus => Self::Subtract,
-            TokenType::Star => Self::Multiply,
-            TokenType::Division => Self::Divide,
+            TokenType::Addition | TokenType::PlusEqual => Self::Add,
+            TokenType::Minus | TokenType::MinusEqual => Self::Subtract,
+            TokenType::Star | TokenType::StarEqual => Self::Multiply,
+            TokenType::Division | TokenType::SlashEqual => Self::Divide,
+            TokenType::TildeSlash => Self::FloorDivide,
+            TokenType::Percent => Self::Modulo,
+            TokenType::StarStar => Self::Power,
             TokenType::Bang => Self::Bang,
             TokenType::EqualEqual => Self::Equals,
             TokenType::BangEqual => Self::NotEquals,
@@ -130,6 +289,8 @@ impl Op {
             TokenType::Greater => Self::GreaterThan,
             TokenType::LessEqual => Self::LessThanOrEquals,
             TokenType::GreaterEqual => Self::GreaterThanOrEquals,
+            TokenType::And => Self::And,
+            TokenType::Or => Self::Or,
             TokenType::LeftParen => Self::Call,
             _ => unreachable!("{:?}", token_type),
         }
@@ -143,14 +304,19 @@ impl Display for Op {
             Op::Subtract => write!(f, "-"),
             Op::Multiply => write!(f, "*"),
             Op::Divide => write!(f, "/"),
+            Op::FloorDivide => write!(f, "~/"),
+            Op::Modulo => write!(f, "%"),
+            Op::Power => write!(f, "**"),
             Op::Bang => write!(f, "!"),
-            Op::Equals => write!(f, "="),
+            Op::Equals => write!(f, "=="),
             Op::NotEquals => write!(f, "!="),
             Op::Assign => write!(f, "="),
             Op::LessThan => write!(f, "<"),
             Op::GreaterThan => write!(f, ">"),
             Op::LessThanOrEquals => write!(f, "<="),
             Op::GreaterThanOrEquals => write!(f, ">="),
+            Op::And => write!(f, "&&"),
+            Op::Or => write!(f, "||"),
             Op::Call => write!(f, "()"),
         }
     }
@@ -159,9 +325,30 @@ impl Display for Op {
 impl Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Statement::Let { name, value } => write!(f, "let {} = {}", name, value),
-            Statement::Return { value } => write!(f, "return {}", value),
-            Statement::StatmentExpression { value } => write!(f, "{}", value),
+            Statement::Let { name, value, .. } => write!(f, "let {} = {}", name, value),
+            Statement::Const { name, value, .. } => write!(f, "const {} = {}", name, value),
+            Statement::Return { value, .. } => write!(f, "return {}", value),
+            Statement::StatmentExpression { value, .. } => write!(f, "{}", value),
+            Statement::While {
+                condition, body, ..
+            } => write!(f, "while({}) {{ {} }}", condition, body),
+            Statement::For {
+                variable,
+                iterable,
+                body,
+                ..
+            } => write!(f, "for {} in {} {{ {} }}", variable, iterable, body),
+            Statement::Break { .. } => write!(f, "break"),
+            Statement::Continue { .. } => write!(f, "continue"),
+            Statement::Function { name, value, .. } => {
+                let literal = value.to_string();
+                write!(
+                    f,
+                    "fn {}{}",
+                    name,
+                    literal.strip_prefix("fn").unwrap_or(&literal)
+                )
+            }
         }
     }
 }
@@ -169,10 +356,11 @@ impl Display for Statement {
 impl Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Expression::Number(n) => write!(f, "{}", n),
-            Expression::String(s) => write!(f, "{}", s),
-            Expression::Indentifier(i) => write!(f, "{}", i),
-            Expression::Boolean(b) => write!(f, "{}", b),
+            Expression::Integer(n, _) => write!(f, "{}", n),
+            Expression::Float(n, _) => write!(f, "{:?}", n),
+            Expression::String(s, _) => write!(f, "{}", s),
+            Expression::Indentifier(i, _) => write!(f, "{}", i),
+            Expression::Boolean(b, _) => write!(f, "{}", b),
             Expression::FunctionLiteral {
                 Token: _,
                 Parameters,
@@ -181,7 +369,7 @@ impl Display for Expression {
                 let params = match Parameters {
                     Some(v) => v
                         .iter()
-                        .map(|exp| exp.to_string())
+                        .map(|param| param.to_string())
                         .collect::<Vec<String>>()
                         .join(", "),
                     None => String::from(""),
@@ -217,9 +405,84 @@ impl Display for Expression {
                 };
                 write!(f, "{}{}{}", Left, Op, right)
             }
-            _ => todo!(),
+            Expression::PrefixExpression {
+                Token: _,
+                Op,
+                Right,
+            } => {
+                let right = match *Right.clone() {
+                    Some(v) => v.to_string(),
+                    None => todo!(),
+                };
+                write!(f, "{}{}", Op, right)
+            }
+            Expression::CallExpression {
+                Token: _,
+                Function,
+                Arguments,
+                NamedArguments,
+            } => {
+                let args = Arguments
+                    .iter()
+                    .flatten()
+                    .map(|exp| exp.to_string())
+                    .chain(
+                        NamedArguments
+                            .iter()
+                            .map(|(name, value)| format!("{}: {}", name.literal, value)),
+                    )
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{}({})", Function, args)
+            }
+            Expression::ArrayLiteral { Token: _, Elements } => {
+                let elements = Elements
+                    .iter()
+                    .map(|exp| exp.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "[{}]", elements)
+            }
+            Expression::IndexExpression {
+                Token: _,
+                Left,
+                Index,
+            } => write!(f, "{}[{}]", Left, Index),
+            Expression::HashLiteral { Token: _, Pairs } => {
+                let pairs = Pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{{{}}}", pairs)
+            }
+            Expression::InterpolatedString { Token: _, Parts } => {
+                let parts = Parts
+                    .iter()
+                    .map(|part| match part {
+                        Expression::String(s, _) => s.clone(),
+                        expression => format!("${{{}}}", expression),
+                    })
+                    .collect::<String>();
+                write!(f, "{}", parts)
+            }
+            Expression::AssignExpression {
+                Token: _,
+                Target,
+                Op: Op::Assign,
+                Value,
+            } => write!(f, "{} = {}", Target, Value),
+            Expression::AssignExpression {
+                Token: _,
+                Target,
+                Op,
+                Value,
+            } => write!(f, "{} {}= {}", Target, Op, Value),
         }
     }
 }
 
 pub type Program = Vec<Statement>;
+
+/// `name: value` in a call, with the token of the name
+pub type NamedArgument = (Token, Expression);
@@ -0,0 +1,302 @@
+use crate::eval::object::{EvalError, StackFrame};
+use crate::parser::ParseError;
+use crate::token::Span;
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// The source code spans can point into, made of every file and REPL input that was read. Each
+/// source starts at its own byte offset, so spans from different sources never overlap
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    sources: Vec<Source>,
+}
+
+#[derive(Debug)]
+struct Source {
+    name: String,
+    text: String,
+    /// Byte offset of the start of the text
+    offset: usize,
+    /// Line number of the first line of the text
+    line: usize,
+}
+
+impl SourceMap {
+    /// Adds `text`, read from `name`, and returns the position its lexer should start at. Text
+    /// added under the name of an earlier source continues its line numbers, like the inputs of
+    /// the REPL do
+    pub fn add(&mut self, name: &str, text: &str) -> Span {
+        // The gap keeps the end of a source, where spans at the end of the file point, apart
+        // from the start of the next one
+        let offset = self
+            .sources
+            .last()
+            .map_or(0, |last| last.offset + last.text.len() + 1);
+        let line = self
+            .sources
+            .iter()
+            .rfind(|source| source.name == name)
+            .map_or(1, |source| source.line + source.text.lines().count());
+
+        self.sources.push(Source {
+            name: name.to_string(),
+            text: text.to_string(),
+            offset,
+            line,
+        });
+
+        Span::new(offset, offset, line, 1)
+    }
+
+    /// Returns the source `span` points into
+    fn find(&self, span: Span) -> Option<&Source> {
+        self.sources.iter().find(|source| {
+            (source.offset..=source.offset + source.text.len()).contains(&span.start)
+        })
+    }
+
+    /// Describes where `span` is, as `file:line:column`
+    fn location(&self, span: Span) -> String {
+        match self.find(span) {
+            Some(source) => format!("{}:{}:{}", source.name, span.line, span.column),
+            None => format!("{}:{}", span.line, span.column),
+        }
+    }
+}
+
+/// An error ready to be shown to the user, rendered rustc-style with the offending source line
+/// and a caret under the span
+#[derive(PartialEq, Debug, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Option<Span>,
+    /// The calls the error happened in, innermost first, with how many times in a row each one
+    /// was repeated
+    pub trace: Vec<(StackFrame, usize)>,
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(e: &ParseError) -> Self {
+        Self {
+            message: e.to_string(),
+            span: Some(e.span()),
+            trace: Vec::new(),
+        }
+    }
+}
+
+impl From<&EvalError> for Diagnostic {
+    fn from(e: &EvalError) -> Self {
+        Self {
+            message: e.to_string(),
+            span: e.span,
+            // Deep recursion would otherwise repeat the same note for every call
+            trace: e
+                .trace
+                .chunk_by(|a, b| a == b)
+                .map(|frames| (frames[0].clone(), frames.len()))
+                .collect(),
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic with the source its span points into. `colour` adds ANSI colour
+    /// codes and should only be set when writing to a terminal
+    pub fn render(&self, sources: &SourceMap, colour: bool) -> String {
+        let (red, blue, bold, reset) = if colour {
+            (RED, BLUE, BOLD, RESET)
+        } else {
+            ("", "", "", "")
+        };
+
+        let mut out = format!("{red}error{reset}{bold}: {}{reset}\n", self.message);
+
+        let found = self
+            .span
+            .filter(|span| span.line > 0)
+            .and_then(|span| Some((span, sources.find(span)?)));
+        let (span, source) = match found {
+            Some(found) => found,
+            None => {
+                // Without a span the error is about the source that was read last
+                let file = sources.sources.last().map_or("", |source| &source.name);
+                out.push_str(&format!("{blue} --> {reset}{}\n", file));
+                self.render_notes(&mut out, sources, "", blue, reset);
+                return out;
+            }
+        };
+
+        let line = source
+            .text
+            .lines()
+            .nth(span.line - source.line)
+            .unwrap_or("");
+        let line_number = span.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+
+        // Keep tabs in the padding so the caret lines up with the source line
+        let padding: String = line
+            .chars()
+            .take(span.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let underlined = source
+            .text
+            .get(span.start - source.offset..span.end - source.offset)
+            .unwrap_or("")
+            .lines()
+            .next()
+            .unwrap_or("")
+            .chars()
+            .count();
+
+        out.push_str(&format!(
+            "{gutter}{blue}--> {reset}{}\n",
+            sources.location(span)
+        ));
+        out.push_str(&format!("{gutter} {blue}|{reset}\n"));
+        out.push_str(&format!("{blue}{line_number} |{reset} {}\n", line));
+        out.push_str(&format!(
+            "{gutter} {blue}|{reset} {}{red}{}{reset}\n",
+            padding,
+            "^".repeat(underlined.max(1))
+        ));
+        self.render_notes(&mut out, sources, &gutter, blue, reset);
+
+        out
+    }
+
+    fn render_notes(
+        &self,
+        out: &mut String,
+        sources: &SourceMap,
+        gutter: &str,
+        blue: &str,
+        reset: &str,
+    ) {
+        for (frame, count) in &self.trace {
+            let repeated = match count {
+                1 => String::new(),
+                n => format!(" ({} times)", n),
+            };
+            out.push_str(&format!(
+                "{gutter} {blue}={reset} note: in `{}` called at {}{}\n",
+                frame.function,
+                sources.location(frame.span),
+                repeated
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::object::EvalErrorKind;
+    use pretty_assertions::assert_eq as p_assert_eq;
+
+    fn frame(function: &str, span: Span) -> StackFrame {
+        StackFrame {
+            function: function.to_string(),
+            span,
+        }
+    }
+
+    #[test]
+    fn render_diagnostic() {
+        let source = "let a = 1\nlet b = a +\tfoobar\n";
+        let mut sources = SourceMap::default();
+        sources.add("test.yaf", source);
+        let diagnostic = Diagnostic {
+            message: "identifier not found: foobar".to_string(),
+            span: Some(Span::new(22, 28, 2, 13)),
+            trace: vec![(frame("f", Span::new(8, 9, 1, 9)), 1)],
+        };
+
+        p_assert_eq!(
+            diagnostic.render(&sources, false),
+            "error: identifier not found: foobar
+ --> test.yaf:2:13
+  |
+2 | let b = a +\tfoobar
+  |            \t^^^^^^
+  = note: in `f` called at test.yaf:1:9
+"
+        );
+    }
+
+    #[test]
+    fn render_diagnostic_across_sources() {
+        let mut sources = SourceMap::default();
+        let first = sources.add("<repl>", "let f = fn() {\n  g()\n}\n");
+        let library = sources.add("lib.yaf", "let g = fn() { h }\n");
+        let last = sources.add("<repl>", "f()\n");
+        p_assert_eq!(
+            (first, library, last),
+            (
+                Span::new(0, 0, 1, 1),
+                Span::new(24, 24, 1, 1),
+                Span::new(44, 44, 4, 1)
+            )
+        );
+
+        let diagnostic = Diagnostic {
+            message: "identifier not found: h".to_string(),
+            span: Some(Span::new(39, 40, 1, 16)),
+            trace: vec![
+                (frame("g", Span::new(17, 18, 2, 3)), 1),
+                (frame("f", Span::new(44, 45, 4, 1)), 1),
+            ],
+        };
+
+        p_assert_eq!(
+            diagnostic.render(&sources, false),
+            "error: identifier not found: h
+ --> lib.yaf:1:16
+  |
+1 | let g = fn() { h }
+  |                ^
+  = note: in `g` called at <repl>:2:3
+  = note: in `f` called at <repl>:4:1
+"
+        );
+    }
+
+    #[test]
+    fn repeated_frames_are_collapsed() {
+        let mut error = EvalError::new(EvalErrorKind::StackOverflow { limit: 3 });
+        for (function, start) in [("f", 0), ("f", 0), ("g", 0), ("g", 4)] {
+            error = error.with_frame(function.to_string(), Span::new(start, start + 1, 1, 3));
+        }
+
+        p_assert_eq!(
+            Diagnostic::from(&error).trace,
+            vec![
+                (frame("f", Span::new(0, 1, 1, 3)), 2),
+                (frame("g", Span::new(0, 1, 1, 3)), 1),
+                (frame("g", Span::new(4, 5, 1, 3)), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn render_diagnostic_without_span() {
+        let mut sources = SourceMap::default();
+        sources.add("test.yaf", "");
+        let diagnostic = Diagnostic {
+            message: "something went wrong".to_string(),
+            span: None,
+            trace: Vec::new(),
+        };
+
+        p_assert_eq!(
+            diagnostic.render(&sources, false),
+            "error: something went wrong\n --> test.yaf\n"
+        );
+    }
+}
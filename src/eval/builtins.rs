@@ -0,0 +1,642 @@
+use std::io::{self, BufRead, Write};
+
+use super::object::{Builtin, EvalError, EvalErrorKind, HashKey, Object};
+
+const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "len",
+        func: len,
+    },
+    Builtin {
+        name: "print",
+        func: print,
+    },
+    Builtin {
+        name: "println",
+        func: println,
+    },
+    Builtin {
+        name: "type",
+        func: type_of,
+    },
+    Builtin {
+        name: "str",
+        func: str,
+    },
+    Builtin {
+        name: "num",
+        func: num,
+    },
+    Builtin {
+        name: "int",
+        func: int,
+    },
+    Builtin {
+        name: "float",
+        func: float,
+    },
+    Builtin {
+        name: "input",
+        func: input,
+    },
+    Builtin {
+        name: "push",
+        func: push,
+    },
+    Builtin {
+        name: "first",
+        func: first,
+    },
+    Builtin {
+        name: "last",
+        func: last,
+    },
+    Builtin {
+        name: "rest",
+        func: rest,
+    },
+    Builtin {
+        name: "slice",
+        func: slice,
+    },
+    Builtin {
+        name: "concat",
+        func: concat,
+    },
+    Builtin {
+        name: "keys",
+        func: keys,
+    },
+    Builtin {
+        name: "values",
+        func: values,
+    },
+    Builtin {
+        name: "has",
+        func: has,
+    },
+    Builtin {
+        name: "delete",
+        func: delete,
+    },
+    Builtin {
+        name: "split",
+        func: split,
+    },
+    Builtin {
+        name: "join",
+        func: join_strings,
+    },
+    Builtin {
+        name: "trim",
+        func: trim,
+    },
+    Builtin {
+        name: "upper",
+        func: upper,
+    },
+    Builtin {
+        name: "lower",
+        func: lower,
+    },
+    Builtin {
+        name: "replace",
+        func: replace,
+    },
+    Builtin {
+        name: "contains",
+        func: contains,
+    },
+    Builtin {
+        name: "starts_with",
+        func: starts_with,
+    },
+    Builtin {
+        name: "ends_with",
+        func: ends_with,
+    },
+    Builtin {
+        name: "find",
+        func: find,
+    },
+    Builtin {
+        name: "chars",
+        func: chars,
+    },
+    Builtin {
+        name: "format",
+        func: format,
+    },
+];
+
+/// Returns the builtin function called `name`, identifiers only resolve to builtins when no
+/// variable with the same name is in scope
+pub fn lookup(name: &str) -> Option<Object> {
+    BUILTINS
+        .iter()
+        .find(|builtin| builtin.name == name)
+        .cloned()
+        .map(Object::Builtin)
+}
+
+fn check_arity(args: &[Object], want: usize) -> Result<(), EvalError> {
+    if args.len() == want {
+        Ok(())
+    } else {
+        Err(EvalError::new(EvalErrorKind::ArityMismatch {
+            got: args.len(),
+            want,
+        }))
+    }
+}
+
+/// Like `check_arity` for builtins with optional arguments, `max` is None when any number of
+/// arguments can follow the first `min`
+fn check_arity_range(args: &[Object], min: usize, max: Option<usize>) -> Result<(), EvalError> {
+    let got = args.len();
+    if got >= min && max.is_none_or(|max| got <= max) {
+        Ok(())
+    } else {
+        Err(EvalError::new(EvalErrorKind::ArityOutOfRange {
+            got,
+            min,
+            max,
+        }))
+    }
+}
+
+fn invalid(function: &str, message: String) -> EvalError {
+    EvalError::new(EvalErrorKind::InvalidArgument {
+        function: function.to_string(),
+        message,
+    })
+}
+
+fn unsupported(function: &str, arg: &Object) -> EvalError {
+    EvalError::new(EvalErrorKind::UnsupportedArgument {
+        function: function.to_string(),
+        got: arg.type_info(),
+    })
+}
+
+/// Returns the string argument at `position`
+fn string_arg<'a>(
+    function: &str,
+    args: &'a [Object],
+    position: usize,
+) -> Result<&'a str, EvalError> {
+    match &args[position] {
+        Object::String(s) => Ok(s),
+        other => Err(unsupported(function, other)),
+    }
+}
+
+fn join(args: &[Object]) -> String {
+    args.iter()
+        .map(|arg| arg.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn len(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    match &args[0] {
+        Object::String(s) => Ok(Object::Int(s.chars().count() as i64)),
+        Object::Array(elements) => Ok(Object::Int(elements.len() as i64)),
+        Object::Hash(pairs) => Ok(Object::Int(pairs.len() as i64)),
+        other => Err(unsupported("len", other)),
+    }
+}
+
+fn print(args: Vec<Object>) -> Result<Object, EvalError> {
+    print!("{}", join(&args));
+    let _ = io::stdout().flush();
+
+    Ok(Object::Nil)
+}
+
+fn println(args: Vec<Object>) -> Result<Object, EvalError> {
+    println!("{}", join(&args));
+
+    Ok(Object::Nil)
+}
+
+fn type_of(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    Ok(Object::String(args[0].type_info()))
+}
+
+fn str(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    Ok(Object::String(args[0].to_string()))
+}
+
+/// Converts a string to an integer when it is one and to a float otherwise
+fn num(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    match &args[0] {
+        Object::Int(_) | Object::Float(_) => Ok(args[0].clone()),
+        Object::String(s) => {
+            if let Ok(n) = s.trim().parse::<i64>() {
+                Ok(Object::Int(n))
+            } else {
+                parse_float("num", s)
+            }
+        }
+        other => Err(unsupported("num", other)),
+    }
+}
+
+/// Converts to an integer, floats are truncated towards zero
+fn int(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    match &args[0] {
+        Object::Int(n) => Ok(Object::Int(*n)),
+        Object::Float(n) if n.is_finite() && (i64::MIN as f64..i64::MAX as f64).contains(n) => {
+            Ok(Object::Int(n.trunc() as i64))
+        }
+        Object::Float(n) => Err(invalid(
+            "int",
+            format!("{:?} does not fit in an integer", n),
+        )),
+        Object::Boolean(b) => Ok(Object::Int(i64::from(*b))),
+        Object::String(s) => s
+            .trim()
+            .parse::<i64>()
+            .map(Object::Int)
+            .map_err(|_| invalid("int", format!("could not parse \"{}\" as an integer", s))),
+        other => Err(unsupported("int", other)),
+    }
+}
+
+fn float(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    match &args[0] {
+        Object::Int(n) => Ok(Object::Float(*n as f64)),
+        Object::Float(n) => Ok(Object::Float(*n)),
+        Object::Boolean(b) => Ok(Object::Float(f64::from(u8::from(*b)))),
+        Object::String(s) => parse_float("float", s),
+        other => Err(unsupported("float", other)),
+    }
+}
+
+fn parse_float(function: &str, s: &str) -> Result<Object, EvalError> {
+    s.trim()
+        .parse::<f64>()
+        .map(Object::Float)
+        .map_err(|_| invalid(function, format!("could not parse \"{}\" as a number", s)))
+}
+
+/// Reads a line from stdin, printing the optional prompt first. Returns nil at the end of input
+fn input(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity_range(&args, 0, Some(1))?;
+
+    if let Some(prompt) = args.first() {
+        print!("{}", prompt);
+        let _ = io::stdout().flush();
+    }
+
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(0) | Err(_) => Ok(Object::Nil),
+        Ok(_) => Ok(Object::String(
+            line.trim_end_matches(['\n', '\r']).to_string(),
+        )),
+    }
+}
+
+/// Returns a new array with `value` appended, the array passed in is left unchanged
+fn push(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 2)?;
+
+    match &args[0] {
+        Object::Array(elements) => {
+            let mut elements = elements.clone();
+            elements.push(args[1].clone());
+            Ok(Object::Array(elements))
+        }
+        other => Err(unsupported("push", other)),
+    }
+}
+
+fn first(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    match &args[0] {
+        Object::Array(elements) => Ok(elements.first().cloned().unwrap_or(Object::Nil)),
+        other => Err(unsupported("first", other)),
+    }
+}
+
+fn last(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    match &args[0] {
+        Object::Array(elements) => Ok(elements.last().cloned().unwrap_or(Object::Nil)),
+        other => Err(unsupported("last", other)),
+    }
+}
+
+/// Returns every element but the first
+fn rest(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    match &args[0] {
+        Object::Array(elements) => Ok(Object::Array(elements.iter().skip(1).cloned().collect())),
+        other => Err(unsupported("rest", other)),
+    }
+}
+
+/// `slice(array, start, end)` returns the elements from `start` up to but not including `end`,
+/// `end` defaults to the length. Negative bounds count from the end and bounds outside of the
+/// array are clamped to it. Strings are sliced by character
+fn slice(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity_range(&args, 2, Some(3))?;
+
+    match &args[0] {
+        Object::Array(elements) => {
+            let (start, end) = slice_bounds(&args[1..], elements.len())?;
+            Ok(Object::Array(elements[start..end].to_vec()))
+        }
+        Object::String(s) => {
+            let (start, end) = slice_bounds(&args[1..], s.chars().count())?;
+            Ok(Object::String(
+                s.chars().skip(start).take(end - start).collect(),
+            ))
+        }
+        other => Err(unsupported("slice", other)),
+    }
+}
+
+fn slice_bounds(bounds: &[Object], length: usize) -> Result<(usize, usize), EvalError> {
+    let mut resolved = [0, length];
+
+    for (position, bound) in bounds.iter().enumerate() {
+        let bound = match bound {
+            Object::Int(n) => *n,
+            Object::Float(_) => {
+                return Err(EvalError::new(EvalErrorKind::InvalidIndex {
+                    index: bound.to_string(),
+                }))
+            }
+            other => return Err(unsupported("slice", other)),
+        };
+
+        let bound = if bound < 0 {
+            bound + length as i64
+        } else {
+            bound
+        };
+        resolved[position] = bound.clamp(0, length as i64) as usize;
+    }
+
+    Ok((resolved[0], resolved[1].max(resolved[0])))
+}
+
+/// Joins any number of arrays into a new one
+fn concat(args: Vec<Object>) -> Result<Object, EvalError> {
+    let mut result = Vec::new();
+
+    for arg in &args {
+        match arg {
+            Object::Array(elements) => result.extend(elements.iter().cloned()),
+            other => return Err(unsupported("concat", other)),
+        }
+    }
+
+    Ok(Object::Array(result))
+}
+
+/// Returns the keys of a hash in order
+fn keys(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    match &args[0] {
+        Object::Hash(pairs) => Ok(Object::Array(
+            pairs.keys().map(HashKey::to_object).collect(),
+        )),
+        other => Err(unsupported("keys", other)),
+    }
+}
+
+/// Returns the values of a hash, in the order of their keys
+fn values(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    match &args[0] {
+        Object::Hash(pairs) => Ok(Object::Array(pairs.values().cloned().collect())),
+        other => Err(unsupported("values", other)),
+    }
+}
+
+fn has(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 2)?;
+
+    match &args[0] {
+        Object::Hash(pairs) => Ok(Object::Boolean(
+            pairs.contains_key(&HashKey::from_object(&args[1])?),
+        )),
+        other => Err(unsupported("has", other)),
+    }
+}
+
+/// Returns a new hash without `key`, the hash passed in is left unchanged
+fn delete(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 2)?;
+
+    match &args[0] {
+        Object::Hash(pairs) => {
+            let mut pairs = pairs.clone();
+            pairs.remove(&HashKey::from_object(&args[1])?);
+            Ok(Object::Hash(pairs))
+        }
+        other => Err(unsupported("delete", other)),
+    }
+}
+
+/// `split(string, separator)` splits around every occurrence of `separator`, without a separator
+/// it splits around runs of whitespace
+fn split(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity_range(&args, 1, Some(2))?;
+
+    let s = string_arg("split", &args, 0)?;
+    let parts: Vec<Object> = match args.get(1) {
+        None => s
+            .split_whitespace()
+            .map(|part| Object::String(part.to_string()))
+            .collect(),
+        Some(_) => {
+            let separator = string_arg("split", &args, 1)?;
+            if separator.is_empty() {
+                return Err(invalid("split", "separator is empty".to_string()));
+            }
+            s.split(separator)
+                .map(|part| Object::String(part.to_string()))
+                .collect()
+        }
+    };
+
+    Ok(Object::Array(parts))
+}
+
+/// `join(array, separator)` joins the elements of an array into a string, the separator defaults
+/// to the empty string
+fn join_strings(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity_range(&args, 1, Some(2))?;
+
+    let elements = match &args[0] {
+        Object::Array(elements) => elements,
+        other => return Err(unsupported("join", other)),
+    };
+    let separator = match args.get(1) {
+        Some(_) => string_arg("join", &args, 1)?,
+        None => "",
+    };
+
+    Ok(Object::String(
+        elements
+            .iter()
+            .map(|element| element.to_string())
+            .collect::<Vec<String>>()
+            .join(separator),
+    ))
+}
+
+fn trim(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    Ok(Object::String(
+        string_arg("trim", &args, 0)?.trim().to_string(),
+    ))
+}
+
+fn upper(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    Ok(Object::String(
+        string_arg("upper", &args, 0)?.to_uppercase(),
+    ))
+}
+
+fn lower(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    Ok(Object::String(
+        string_arg("lower", &args, 0)?.to_lowercase(),
+    ))
+}
+
+/// `replace(string, from, to)` replaces every occurrence of `from`
+fn replace(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 3)?;
+
+    let s = string_arg("replace", &args, 0)?;
+    let from = string_arg("replace", &args, 1)?;
+    let to = string_arg("replace", &args, 2)?;
+    if from.is_empty() {
+        return Err(invalid("replace", "pattern is empty".to_string()));
+    }
+
+    Ok(Object::String(s.replace(from, to)))
+}
+
+fn contains(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 2)?;
+
+    let s = string_arg("contains", &args, 0)?;
+    Ok(Object::Boolean(
+        s.contains(string_arg("contains", &args, 1)?),
+    ))
+}
+
+fn starts_with(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 2)?;
+
+    let s = string_arg("starts_with", &args, 0)?;
+    Ok(Object::Boolean(s.starts_with(string_arg(
+        "starts_with",
+        &args,
+        1,
+    )?)))
+}
+
+fn ends_with(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 2)?;
+
+    let s = string_arg("ends_with", &args, 0)?;
+    Ok(Object::Boolean(s.ends_with(string_arg(
+        "ends_with",
+        &args,
+        1,
+    )?)))
+}
+
+/// Returns the character index of the first occurrence of a substring, nil when there is none
+fn find(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 2)?;
+
+    let s = string_arg("find", &args, 0)?;
+    let pattern = string_arg("find", &args, 1)?;
+
+    Ok(s.find(pattern)
+        .map(|byte| Object::Int(s[..byte].chars().count() as i64))
+        .unwrap_or(Object::Nil))
+}
+
+/// Splits a string into an array of its characters
+fn chars(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity(&args, 1)?;
+
+    Ok(Object::Array(
+        string_arg("chars", &args, 0)?
+            .chars()
+            .map(|c| Object::String(c.to_string()))
+            .collect(),
+    ))
+}
+
+/// `format(template, values...)` replaces every `{}` in the template with the next value, `{{`
+/// and `}}` stand for literal braces
+fn format(args: Vec<Object>) -> Result<Object, EvalError> {
+    check_arity_range(&args, 1, None)?;
+
+    let template = string_arg("format", &args, 0)?;
+    let mut values = args[1..].iter();
+    let mut result = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                let value = values.next().ok_or_else(|| {
+                    invalid("format", "more placeholders than values".to_string())
+                })?;
+                result.push_str(&value.to_string());
+            }
+            ('{' | '}', _) => {
+                return Err(invalid("format", format!("unmatched `{}` in template", c)))
+            }
+            _ => result.push(c),
+        }
+    }
+
+    if values.next().is_some() {
+        return Err(invalid(
+            "format",
+            "more values than placeholders".to_string(),
+        ));
+    }
+
+    Ok(Object::String(result))
+}
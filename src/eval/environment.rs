@@ -1,27 +1,162 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 
-use super::object::Object;
+use super::object::{EvalErrorKind, Object};
 
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct Environment {
     store: HashMap<String, Object>,
+    /// Names in `store` that were declared with `const`
+    constants: HashSet<String>,
     outer: Option<Env>,
 }
 
 impl Environment {
     pub fn new_enclosed_environment(outer: &Env) -> Self {
-        let mut env: Environment = Default::default();
-        env.outer = Some(Rc::clone(outer));
-        env
+        Environment {
+            outer: Some(Rc::clone(outer)),
+            ..Default::default()
+        }
     }
 
-    pub fn get(&self, name: &str) -> Option<Object> {
-        self.store.get(name).cloned()
+    /// Resolves `name` through the scope chain, the innermost scope that declares it wins
+    pub fn lookup(&self, name: &str) -> Option<Object> {
+        match self.store.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.outer.as_ref()?.borrow().lookup(name),
+        }
     }
 
-    pub fn set(&mut self, name: String, value: Object) {
+    /// Declares `name` in this scope, a `let` can be declared again but a constant can not
+    pub fn define(
+        &mut self,
+        name: String,
+        value: Object,
+        constant: bool,
+    ) -> Result<(), EvalErrorKind> {
+        if self.constants.contains(&name) {
+            return Err(EvalErrorKind::ConstAssignment { name });
+        }
+
+        if constant {
+            self.constants.insert(name.clone());
+        }
         self.store.insert(name, value);
+        Ok(())
+    }
+
+    /// Updates an existing binding in the scope that declared it, looking through the outer
+    /// scopes
+    pub fn assign(&mut self, name: &str, value: Object) -> Result<(), EvalErrorKind> {
+        if let Some(binding) = self.store.get_mut(name) {
+            if self.constants.contains(name) {
+                return Err(EvalErrorKind::ConstAssignment {
+                    name: name.to_string(),
+                });
+            }
+
+            *binding = value;
+            return Ok(());
+        }
+
+        match &self.outer {
+            Some(outer) => outer.borrow_mut().assign(name, value),
+            None => Err(EvalErrorKind::UndeclaredAssignment {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Returns the bindings of this scope sorted by name, without those of outer scopes
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        let mut bindings: Vec<(String, Object)> = self
+            .store
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
     }
 }
 
 pub type Env = Rc<RefCell<Environment>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq as p_assert_eq;
+
+    fn enclosed(outer: &Env) -> Env {
+        Rc::new(RefCell::new(Environment::new_enclosed_environment(outer)))
+    }
+
+    #[test]
+    fn lookup_walks_the_scope_chain() {
+        let global: Env = Rc::new(RefCell::new(Default::default()));
+        global
+            .borrow_mut()
+            .define("a".to_string(), Object::Int(1), false)
+            .unwrap();
+        let function = enclosed(&global);
+        let block = enclosed(&function);
+
+        p_assert_eq!(block.borrow().lookup("a"), Some(Object::Int(1)));
+        p_assert_eq!(block.borrow().lookup("b"), None);
+
+        // Bindings made after the inner scope was created are still visible
+        global
+            .borrow_mut()
+            .define("b".to_string(), Object::Int(2), false)
+            .unwrap();
+        p_assert_eq!(block.borrow().lookup("b"), Some(Object::Int(2)));
+    }
+
+    #[test]
+    fn define_shadows_outer_bindings() {
+        let global: Env = Rc::new(RefCell::new(Default::default()));
+        global
+            .borrow_mut()
+            .define("a".to_string(), Object::Int(1), true)
+            .unwrap();
+        let inner = enclosed(&global);
+        inner
+            .borrow_mut()
+            .define("a".to_string(), Object::Int(2), false)
+            .unwrap();
+
+        p_assert_eq!(inner.borrow().lookup("a"), Some(Object::Int(2)));
+        p_assert_eq!(global.borrow().lookup("a"), Some(Object::Int(1)));
+        p_assert_eq!(
+            global
+                .borrow_mut()
+                .define("a".to_string(), Object::Int(3), false),
+            Err(EvalErrorKind::ConstAssignment {
+                name: "a".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn assign_updates_the_declaring_scope() {
+        let global: Env = Rc::new(RefCell::new(Default::default()));
+        global
+            .borrow_mut()
+            .define("a".to_string(), Object::Int(1), false)
+            .unwrap();
+        let inner = enclosed(&global);
+
+        inner.borrow_mut().assign("a", Object::Int(5)).unwrap();
+        p_assert_eq!(global.borrow().lookup("a"), Some(Object::Int(5)));
+        p_assert_eq!(inner.borrow().bindings(), vec![]);
+
+        p_assert_eq!(
+            inner.borrow_mut().assign("b", Object::Int(1)),
+            Err(EvalErrorKind::UndeclaredAssignment {
+                name: "b".to_string()
+            })
+        );
+    }
+}
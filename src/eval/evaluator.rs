@@ -1,34 +1,127 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    rc::Rc,
+};
 
 use crate::{
-    ast::{self, Expression, Node, Op, Statement},
+    ast::{self, Expression, Node, Op, Parameter, Statement},
     eval::environment::Environment,
+    token::{Span, Token},
 };
 
 #[cfg(test)]
 use pretty_assertions::assert_eq as p_assert_eq;
 
 use super::{
+    builtins,
     environment::Env,
-    object::{EvalError, Object},
+    object::{EvalError, EvalErrorKind, HashKey, Object},
 };
 
+/// How many calls can be nested before evaluation stops with a stack overflow error
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 2_000;
+
+thread_local! {
+    static MAX_CALL_DEPTH: Cell<usize> = const { Cell::new(DEFAULT_MAX_CALL_DEPTH) };
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+    /// The lowest address the native stack may grow down to, 0 when the stack size is not known
+    static STACK_LIMIT: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The longest string, in bytes, an operator can build
+const MAX_STRING_LENGTH: usize = 1024 * 1024 * 1024;
+
+/// Native stack left free below the limit for the builtins and for unwinding an error
+const STACK_RESERVE: usize = 1024 * 1024;
+
+/// Sets how many calls can be nested on the current thread. Every call uses some of the native
+/// stack, so the thread needs a stack that is large enough for the limit
+pub fn set_max_call_depth(limit: usize) {
+    MAX_CALL_DEPTH.with(|max| max.set(limit));
+}
+
+/// Tells the evaluator how much native stack the current thread has, it has to be called near the
+/// start of the thread. Expressions nested deeply enough use up the stack before the call depth
+/// limit is reached, evaluation then stops with a stack overflow error instead of crashing
+pub fn set_stack_size(size: usize) {
+    let limit = stack_address().saturating_sub(size) + STACK_RESERVE;
+    STACK_LIMIT.with(|stack_limit| stack_limit.set(limit));
+}
+
+/// The address of a local variable, it moves down as the native stack grows
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// Stops the evaluation when the native stack is about to run out
+fn check_stack() -> Result<(), EvalError> {
+    if stack_address() < STACK_LIMIT.with(Cell::get) {
+        let depth = CALL_DEPTH.with(Cell::get);
+        return Err(EvalError::new(EvalErrorKind::StackExhausted { depth }));
+    }
+    Ok(())
+}
+
+/// Counts a function call towards the call depth for as long as it is alive
+struct CallGuard;
+
+impl CallGuard {
+    fn enter() -> Result<Self, EvalError> {
+        let limit = MAX_CALL_DEPTH.with(Cell::get);
+        CALL_DEPTH.with(|depth| {
+            if depth.get() >= limit {
+                return Err(EvalError::new(EvalErrorKind::StackOverflow { limit }));
+            }
+
+            depth.set(depth.get() + 1);
+            Ok(CallGuard)
+        })
+    }
+}
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        CALL_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+/// Unwraps the value of an expression that is used by the one around it. A `break`, `continue`
+/// or `return` evaluated inside it, in the blocks of an `if`, is not a value: it stops the
+/// evaluation of the enclosing statement so it reaches the loop or function it belongs to
+macro_rules! value {
+    ($result:expr) => {
+        match $result? {
+            flow @ (Object::Return(_) | Object::Break | Object::Continue) => return Ok(flow),
+            value => value,
+        }
+    };
+}
+
 pub fn eval(node: Node, ev: &Env) -> Result<Object, EvalError> {
     match node {
         Node::Program(p) => eval_program(p, ev),
-        Node::BlockStatment(b) => eval_program(b.Statement, ev),
+        // Every block gets a scope of its own, closures created inside keep it alive
+        Node::BlockStatment(b) => {
+            let block_env: Env = Rc::new(RefCell::new(Environment::new_enclosed_environment(ev)));
+            eval_program(b.Statement, &block_env)
+        }
         Node::Statment(s) => eval_statment(s, ev),
         Node::Expression(e) => eval_expression(e, ev),
     }
 }
 
 fn eval_program(p: Vec<Statement>, ev: &Env) -> Result<Object, EvalError> {
+    hoist_functions(&p, ev)?;
+
     let mut result: Object = Object::Nil;
     for statment in p {
         let stmt = eval_statment(statment, ev);
         match stmt {
             Ok(s) => match s {
-                Object::Return(_) => return Ok(s),
+                Object::Return(_) | Object::Break | Object::Continue => return Ok(s),
                 _ => result = s,
             },
             Err(e) => return Err(e),
@@ -37,117 +130,544 @@ fn eval_program(p: Vec<Statement>, ev: &Env) -> Result<Object, EvalError> {
     Ok(result)
 }
 
+/// Defines the functions declared in a block before any of its statements run, so they can be
+/// called before their declaration and call each other
+fn hoist_functions(statements: &[Statement], ev: &Env) -> Result<(), EvalError> {
+    for statement in statements {
+        if let Statement::Function {
+            name,
+            value: Expression::FunctionLiteral {
+                Parameters, Body, ..
+            },
+            span,
+        } = statement
+        {
+            let function = Object::Function {
+                Parameters: Parameters.clone(),
+                Body: Body.clone(),
+                env: Rc::clone(ev),
+                name: Some(name.clone()),
+            };
+            ev.borrow_mut()
+                .define(name.clone(), function, false)
+                .map_err(|kind| EvalError::new(kind).with_span(*span))?;
+        }
+    }
+
+    Ok(())
+}
+
 fn eval_statment(s: Statement, ev: &Env) -> Result<Object, EvalError> {
     match s {
-        Statement::Let { name: n, value: v } => {
-            //TODO: Match to check if identifier ??
+        Statement::Let {
+            name: n,
+            value: v,
+            span,
+        } => {
+            let exp = value!(eval_expression(v, ev));
+            ev.borrow_mut()
+                .define(n, exp, false)
+                .map_err(|kind| EvalError::new(kind).with_span(span))?;
 
-            let exp = eval_expression(v, ev)?;
-            let _ = &ev.try_borrow_mut().unwrap().set(n, exp);
+            Ok(Object::Nil)
+        }
+        Statement::Const { name, value, span } => {
+            let value = value!(eval_expression(value, ev));
+            ev.borrow_mut()
+                .define(name, value, true)
+                .map_err(|kind| EvalError::new(kind).with_span(span))?;
 
             Ok(Object::Nil)
         }
-        Statement::Return { value: v } => {
-            let value = eval_expression(v, ev)?;
+        Statement::Return { value: v, .. } => {
+            let value = value!(eval_expression(v, ev));
             Ok(Object::Return(Box::new(value)))
         }
-        Statement::StatmentExpression { value } => eval_expression(value, ev),
+        Statement::StatmentExpression { value, .. } => eval_expression(value, ev),
+        Statement::While {
+            condition, body, ..
+        } => {
+            while is_truthy(value!(eval_expression(condition.clone(), ev))) {
+                match eval(Node::BlockStatment(body.clone()), ev)? {
+                    Object::Break => break,
+                    value @ Object::Return(_) => return Ok(value),
+                    _ => {}
+                }
+            }
+
+            Ok(Object::Nil)
+        }
+        Statement::For {
+            variable,
+            iterable,
+            body,
+            span,
+        } => {
+            let iterable_span = iterable.span();
+            let items = iterate(value!(eval_expression(iterable, ev)))
+                .map_err(|e| e.with_span(iterable_span))?;
+
+            for item in items {
+                // The loop variable lives in the scope of the body, so every iteration has its
+                // own binding for closures to capture
+                let iteration_env: Env =
+                    Rc::new(RefCell::new(Environment::new_enclosed_environment(ev)));
+                iteration_env
+                    .borrow_mut()
+                    .define(variable.clone(), item, false)
+                    .map_err(|kind| EvalError::new(kind).with_span(span))?;
+
+                match eval_program(body.Statement.clone(), &iteration_env)? {
+                    Object::Break => break,
+                    value @ Object::Return(_) => return Ok(value),
+                    _ => {}
+                }
+            }
+
+            Ok(Object::Nil)
+        }
+        Statement::Break { .. } => Ok(Object::Break),
+        Statement::Continue { .. } => Ok(Object::Continue),
+        // Already defined when the block started
+        Statement::Function { .. } => Ok(Object::Nil),
+    }
+}
+
+/// Returns the items a `for` loop goes through: the elements of an array, the characters of a
+/// string or the keys of a hash
+fn iterate(iterable: Object) -> Result<Vec<Object>, EvalError> {
+    match iterable {
+        Object::Array(elements) => Ok(elements),
+        Object::String(s) => Ok(s.chars().map(|c| Object::String(c.to_string())).collect()),
+        Object::Hash(pairs) => Ok(pairs.keys().map(HashKey::to_object).collect()),
+        other => Err(EvalError::new(EvalErrorKind::NotIterable {
+            found: other.type_info(),
+        })),
     }
 }
 
 fn eval_expression(e: Expression, ev: &Env) -> Result<Object, EvalError> {
+    check_stack().map_err(|error| error.with_span(e.span()))?;
+
     match e {
-        Expression::Number(n) => Ok(Object::Integer(n)),
-        Expression::String(s) => Ok(Object::String(s)),
-        Expression::Boolean(b) => Ok(Object::Boolean(b)),
-        Expression::Indentifier(i) => {
-            let val = ev.borrow().get(&i);
+        Expression::Integer(n, _) => Ok(Object::Int(n)),
+        Expression::Float(n, _) => Ok(Object::Float(n)),
+        Expression::String(s, _) => Ok(Object::String(s)),
+        Expression::Boolean(b, _) => Ok(Object::Boolean(b)),
+        Expression::Indentifier(i, span) => {
+            let val = ev.borrow().lookup(&i).or_else(|| builtins::lookup(&i));
             match val {
                 Some(v) => Ok(v),
-                None => Err(format!("identifier not found: {}", i)),
+                None => {
+                    Err(EvalError::new(EvalErrorKind::UndefinedVariable { name: i })
+                        .with_span(span))
+                }
             }
         }
-        Expression::PrefixExpression {
+        Expression::PrefixExpression { Token, Op, Right } => {
+            let right = value!(eval_expression(Right.expect("eval prefix"), ev));
+            eval_prefix(Op, right).map_err(|e| e.with_span(Token.span))
+        }
+        Expression::InfixExpression {
             Token: _,
-            Op,
+            Left,
+            Op: op @ (Op::And | Op::Or),
             Right,
         } => {
-            let right = eval_expression(Right.expect("eval prefix"), ev)?;
-            eval_prefix(Op, right)
+            let left = value!(eval_expression(*Left, ev));
+
+            // The right side is only evaluated when the left one does not decide the result
+            if is_truthy(left.clone()) == (op == Op::Or) {
+                Ok(left)
+            } else {
+                eval_expression(Right.unwrap(), ev)
+            }
         }
         Expression::InfixExpression {
-            Token: _,
+            Token,
             Left,
             Op,
             Right,
         } => {
-            let left = eval_expression(*Left, ev)?;
-            let right = eval_expression(Right.unwrap(), ev)?;
+            let left = value!(eval_expression(*Left, ev));
+            let right = value!(eval_expression(Right.unwrap(), ev));
 
-            eval_infix_expression(left, Op, right)
+            eval_infix_expression(left, Op, right).map_err(|e| e.with_span(Token.span))
         }
         Expression::IfExpression {
             Token: _,
             Condition,
             Consequence,
             Alternative,
-        } => eval_ifelse_expression(Condition, Consequence, Alternative, ev),
+        } => eval_ifelse_expression(*Condition, Consequence, Alternative, ev),
         Expression::FunctionLiteral {
             Token: _,
             Parameters,
             Body,
-        } => {
-            return Ok(Object::Function {
-                Parameters,
-                Body,
-                env: ev.clone(),
-            })
-        }
+        } => Ok(Object::Function {
+            Parameters,
+            Body,
+            env: ev.clone(),
+            name: None,
+        }),
         Expression::CallExpression {
-            Token: _,
+            Token,
             Function,
             Arguments,
+            NamedArguments,
         } => {
-            let func = eval_expression(*Function, ev)?;
+            let call_span = Function.span();
+            let callee = match *Function {
+                Expression::Indentifier(ref n, _) => Some(n.clone()),
+                _ => None,
+            };
+            let func = value!(eval_expression(*Function, ev));
 
             let mut args = vec![];
             if let Some(expr) = Arguments {
                 for exp in expr {
-                    args.push(eval_expression(exp, ev)?)
+                    args.push(value!(eval_expression(exp, ev)))
                 }
             }
 
-            let mut enclosed_env = Environment::new_enclosed_environment(ev);
+            let mut named = vec![];
+            for (name, exp) in NamedArguments {
+                named.push((name, value!(eval_expression(exp, ev))));
+            }
 
             match func {
                 Object::Function {
                     Parameters,
                     Body,
-                    env: _,
+                    env,
+                    name,
                 } => {
-                    if let Some(param) = Parameters {
-                        param
-                            .iter()
-                            .enumerate()
-                            .for_each(|(indx, param)| match param {
-                                Expression::Indentifier(n) => {
-                                    enclosed_env.set(n.clone(), args[indx].clone())
-                                }
-                                _ => todo!("Not sure what to do here yet?"),
-                            });
-                    }
+                    // Traces show the name a function was declared with, else the one it was
+                    // called by
+                    let name = name.or(callee).unwrap_or_else(|| "<anonymous>".to_string());
+                    let parameters = Parameters.unwrap_or_default();
+                    let values = match_arguments(&parameters, args, named, &name)
+                        .map_err(|e| e.with_span(Token.span))?;
+
+                    let _call = CallGuard::enter().map_err(|e| e.with_span(Token.span))?;
+
+                    // The body sees the scope the function was defined in, not the caller's
+                    let enclosed_env: Env =
+                        Rc::new(RefCell::new(Environment::new_enclosed_environment(&env)));
+                    let evaluated = bind_arguments(parameters, values, &enclosed_env)
+                        .and_then(|bound| match bound {
+                            // A `return` in a default value returns from the call
+                            value @ Object::Return(_) => Ok(value),
+                            // The body shares the scope of the call with the parameters
+                            _ => eval_program(Body.Statement, &enclosed_env),
+                        })
+                        .map_err(|e| e.with_frame(name, call_span))?;
+
+                    unwrap_return_value(evaluated)
+                }
+                Object::Builtin(builtin) => match named.first() {
+                    Some((name, _)) => Err(EvalError::new(EvalErrorKind::UnknownArgument {
+                        function: builtin.name.to_string(),
+                        name: name.literal.clone(),
+                    })
+                    .with_span(name.span)),
+                    None => (builtin.func)(args).map_err(|e| e.with_span(Token.span)),
+                },
+                _ => Err(EvalError::new(EvalErrorKind::NotCallable {
+                    found: func.type_info(),
+                })
+                .with_span(Token.span)),
+            }
+        }
+        Expression::ArrayLiteral { Token: _, Elements } => {
+            let mut elements = vec![];
+            for exp in Elements {
+                elements.push(value!(eval_expression(exp, ev)))
+            }
+
+            Ok(Object::Array(elements))
+        }
+        Expression::IndexExpression { Token, Left, Index } => {
+            let left = value!(eval_expression(*Left, ev));
+            let index = value!(eval_expression(*Index, ev));
+
+            eval_index_expression(left, index).map_err(|e| e.with_span(Token.span))
+        }
+        Expression::HashLiteral { Token: _, Pairs } => {
+            let mut pairs = BTreeMap::new();
+            for (key_expression, value_expression) in Pairs {
+                let key_span = key_expression.span();
+                let key = value!(eval_expression(key_expression, ev));
+                let key = HashKey::from_object(&key).map_err(|e| e.with_span(key_span))?;
 
-                    let evaluated = eval(
-                        Node::BlockStatment(Body),
-                        &Rc::new(RefCell::new(enclosed_env)),
-                    )?;
+                pairs.insert(key, value!(eval_expression(value_expression, ev)));
+            }
 
-                    return unwrap_return_value(evaluated);
+            Ok(Object::Hash(pairs))
+        }
+        Expression::InterpolatedString { Token: _, Parts } => {
+            let mut result = String::new();
+            for part in Parts {
+                match value!(eval_expression(part, ev)) {
+                    Object::String(s) => result.push_str(&s),
+                    value => result.push_str(&value.to_string()),
                 }
-                _ => Err(format!("Expected function")),
             }
+
+            Ok(Object::String(result))
+        }
+        Expression::AssignExpression {
+            Token,
+            Target,
+            Op,
+            Value,
+        } => {
+            let value = value!(eval_expression(*Value, ev));
+
+            assign(*Target, Op, value, ev).map_err(|e| e.with_span(Token.span))
+        }
+    }
+}
+
+/// Stores `value` in `target`, combined with what the target holds when `op` is a compound
+/// assignment. The variable and every index of the target are evaluated once, collections are
+/// values so assigning to an element stores an updated copy of them back in the variable. Returns
+/// the stored value, or the `break`, `continue` or `return` that stopped the evaluation of an index
+fn assign(target: Expression, op: Op, value: Object, ev: &Env) -> Result<Object, EvalError> {
+    // The indices from the outermost one in, `a[i][j]` gives `j` then `i`
+    let mut indices = Vec::new();
+    let mut root = target;
+    while let Expression::IndexExpression { Token, Left, Index } = root {
+        indices.push((Token.span, *Index));
+        root = *Left;
+    }
+    let Expression::Indentifier(name, span) = root else {
+        unreachable!("the parser only accepts assignable targets")
+    };
+
+    let (updated, stored) = if indices.is_empty() && op == Op::Assign {
+        (value.clone(), value)
+    } else {
+        let current = value!(eval_expression(
+            Expression::Indentifier(name.clone(), span),
+            ev
+        ));
+        let mut path = Vec::new();
+        for (index_span, index) in indices.into_iter().rev() {
+            path.push((index_span, value!(eval_expression(index, ev))));
+        }
+        update(current, &path, op, value)?
+    };
+
+    ev.borrow_mut()
+        .assign(&name, updated)
+        .map_err(|kind| EvalError::new(kind).with_span(span))?;
+    Ok(stored)
+}
+
+/// Stores `value` at `path` inside `current`, combined with the element there for a compound
+/// assignment. Returns the updated `current` and the value that was stored
+fn update(
+    current: Object,
+    path: &[(Span, Object)],
+    op: Op,
+    value: Object,
+) -> Result<(Object, Object), EvalError> {
+    let Some(((span, index), rest)) = path.split_first() else {
+        let stored = eval_infix_expression(current, op, value)?;
+        return Ok((stored.clone(), stored));
+    };
+
+    let (element, stored) = if rest.is_empty() && op == Op::Assign {
+        (value.clone(), value)
+    } else {
+        let element = eval_index_expression(current.clone(), index.clone())
+            .map_err(|e| e.with_span(*span))?;
+        update(element, rest, op, value)?
+    };
+    let updated = set_index(current, index.clone(), element).map_err(|e| e.with_span(*span))?;
+
+    Ok((updated, stored))
+}
+
+fn set_index(collection: Object, index: Object, value: Object) -> Result<Object, EvalError> {
+    match (collection, &index) {
+        (Object::Array(mut elements), Object::Int(i)) => {
+            let position = resolve_index(*i, elements.len())?;
+            elements[position] = value;
+            Ok(Object::Array(elements))
+        }
+        (Object::Array(_), Object::Float(_)) => Err(EvalError::new(EvalErrorKind::InvalidIndex {
+            index: index.to_string(),
+        })),
+        (Object::Hash(mut pairs), _) => {
+            pairs.insert(HashKey::from_object(&index)?, value);
+            Ok(Object::Hash(pairs))
+        }
+        (collection, _) => Err(EvalError::new(EvalErrorKind::IndexNotSupported {
+            left: collection.type_info(),
+            index: index.type_info(),
+        })),
+    }
+}
+
+fn eval_index_expression(left: Object, index: Object) -> Result<Object, EvalError> {
+    match (&left, &index) {
+        (Object::Array(elements), Object::Int(i)) => {
+            let position = resolve_index(*i, elements.len())?;
+            Ok(elements[position].clone())
+        }
+        (Object::String(s), Object::Int(i)) => {
+            let position = resolve_index(*i, s.chars().count())?;
+            Ok(Object::String(
+                s.chars()
+                    .nth(position)
+                    .map(String::from)
+                    .unwrap_or_default(),
+            ))
         }
+        (Object::Array(_) | Object::String(_), Object::Float(_)) => {
+            Err(EvalError::new(EvalErrorKind::InvalidIndex {
+                index: index.to_string(),
+            }))
+        }
+        (Object::Hash(pairs), _) => Ok(pairs
+            .get(&HashKey::from_object(&index)?)
+            .cloned()
+            .unwrap_or(Object::Nil)),
+        _ => Err(EvalError::new(EvalErrorKind::IndexNotSupported {
+            left: left.type_info(),
+            index: index.type_info(),
+        })),
+    }
+}
+
+/// Turns the index the user wrote into a position in a collection of `length` elements, negative
+/// indexes count from the end
+pub fn resolve_index(index: i64, length: usize) -> Result<usize, EvalError> {
+    let position = if index < 0 {
+        index + length as i64
+    } else {
+        index
+    };
+
+    if position < 0 || position >= length as i64 {
+        Err(EvalError::new(EvalErrorKind::IndexOutOfBounds {
+            index,
+            length,
+        }))
+    } else {
+        Ok(position as usize)
+    }
+}
+
+/// Checks that a call with `got` arguments gives a value to every parameter without a default,
+/// and has no more arguments than parameters unless there is a rest parameter
+fn check_arity(parameters: &[Parameter], got: usize) -> Result<(), EvalError> {
+    let rest = parameters.iter().any(|p| p.rest);
+    let min = parameters
+        .iter()
+        .filter(|p| p.default.is_none() && !p.rest)
+        .count();
+    let max = parameters.len() - usize::from(rest);
+
+    if got >= min && (rest || got <= max) {
+        return Ok(());
+    }
+
+    let kind = if min == max && !rest {
+        EvalErrorKind::ArityMismatch { got, want: min }
+    } else {
+        EvalErrorKind::ArityOutOfRange {
+            got,
+            min,
+            max: (!rest).then_some(max),
+        }
+    };
+    Err(EvalError::new(kind))
+}
+
+/// Matches the arguments of a call to `function` with its parameters, the positional ones in
+/// order and then the named ones by name. Returns the value of every parameter, None for those
+/// that are left to their default
+fn match_arguments(
+    parameters: &[Parameter],
+    args: Vec<Object>,
+    named: Vec<(Token, Object)>,
+    function: &str,
+) -> Result<Vec<Option<Object>>, EvalError> {
+    let rest = parameters.iter().any(|p| p.rest);
+    // Without named arguments the number of positional ones says everything that is wrong
+    if named.is_empty() || (!rest && args.len() > parameters.len()) {
+        check_arity(parameters, args.len())?;
+    }
+
+    let mut args = args.into_iter();
+    let mut values: Vec<Option<Object>> = parameters
+        .iter()
+        .map(|parameter| match parameter.rest {
+            true => Some(Object::Array(args.by_ref().collect())),
+            false => args.next(),
+        })
+        .collect();
+
+    for (name, value) in named {
+        let position = parameters
+            .iter()
+            .position(|p| p.name == name.literal && !p.rest);
+
+        let kind = match position {
+            Some(position) if values[position].is_none() => {
+                values[position] = Some(value);
+                continue;
+            }
+            Some(_) => EvalErrorKind::DuplicateArgument {
+                function: function.to_string(),
+                name: name.literal,
+            },
+            None => EvalErrorKind::UnknownArgument {
+                function: function.to_string(),
+                name: name.literal,
+            },
+        };
+        return Err(EvalError::new(kind).with_span(name.span));
+    }
+
+    let missing = parameters
+        .iter()
+        .zip(&values)
+        .find(|(parameter, value)| value.is_none() && parameter.default.is_none());
+    if let Some((parameter, _)) = missing {
+        return Err(EvalError::new(EvalErrorKind::MissingArgument {
+            function: function.to_string(),
+            name: parameter.name.clone(),
+        }));
+    }
+
+    Ok(values)
+}
+
+/// Binds the values matched to the parameters of a function in the scope of the call. Defaults
+/// are evaluated in that scope, so they can refer to the parameters before them. Returns Nil, or
+/// the `return` evaluated in a default
+fn bind_arguments(
+    parameters: Vec<Parameter>,
+    values: Vec<Option<Object>>,
+    ev: &Env,
+) -> Result<Object, EvalError> {
+    for (parameter, value) in parameters.into_iter().zip(values) {
+        let value = match (value, parameter.default) {
+            (Some(value), _) => value,
+            (None, Some(default)) => value!(eval_expression(default, ev)),
+            (None, None) => unreachable!("missing arguments were reported"),
+        };
+
+        ev.borrow_mut()
+            .define(parameter.name, value, false)
+            .map_err(|kind| EvalError::new(kind).with_span(parameter.span))?;
     }
+
+    Ok(Object::Nil)
 }
 
 fn unwrap_return_value(obj: Object) -> Result<Object, EvalError> {
@@ -159,12 +679,12 @@ fn unwrap_return_value(obj: Object) -> Result<Object, EvalError> {
 }
 
 fn eval_ifelse_expression(
-    condition: Box<Expression>,
+    condition: Expression,
     consequence: ast::BlockStatment,
     alternative: Option<ast::BlockStatment>,
     ev: &Env,
 ) -> Result<Object, EvalError> {
-    let condition = eval_expression(*condition, ev)?;
+    let condition = value!(eval_expression(condition, ev));
 
     // TODO: Refactor this solution
     if is_truthy(condition) {
@@ -178,36 +698,60 @@ fn eval_ifelse_expression(
 }
 
 fn is_truthy(obj: Object) -> bool {
-    match obj {
-        Object::Nil => return false,
-        Object::Boolean(false) => return false,
-        _ => true,
-    }
+    !matches!(obj, Object::Nil | Object::Boolean(false))
 }
 
 fn eval_infix_expression(left: Object, op: ast::Op, right: Object) -> Result<Object, EvalError> {
     match (&left, &right) {
-        (Object::Integer(ln), Object::Integer(rn)) => eval_int_infix_expression(ln, op, rn),
+        (Object::Int(ln), Object::Int(rn)) => eval_int_infix_expression(*ln, op, *rn),
+        // Integers are promoted to floats when mixed with them
+        (Object::Int(_) | Object::Float(_), Object::Int(_) | Object::Float(_)) => {
+            match (left.as_float(), right.as_float()) {
+                (Some(ln), Some(rn)) => eval_float_infix_expression(ln, op, rn),
+                _ => unreachable!("both sides are numbers"),
+            }
+        }
         (Object::Boolean(lb), Object::Boolean(rb)) => eval_bool_infix_expression(lb, op, rb),
         (Object::String(ls), Object::String(rs)) => eval_string_infix_expression(ls, op, rs),
-        _ => Err(format!(
-            "type mismatch: {} {} {}",
-            left.type_info(),
+        // Repetition, a negative count gives the empty string
+        (Object::String(s), Object::Int(n)) | (Object::Int(n), Object::String(s))
+            if op == Op::Multiply =>
+        {
+            let count = usize::try_from(*n).unwrap_or(0);
+            match s.len().checked_mul(count) {
+                Some(length) if length <= MAX_STRING_LENGTH => Ok(Object::String(s.repeat(count))),
+                _ => Err(EvalError::new(EvalErrorKind::StringTooLong {
+                    max: MAX_STRING_LENGTH,
+                })),
+            }
+        }
+        _ if left.type_info() == right.type_info() => {
+            Err(EvalError::new(EvalErrorKind::UnknownOperator {
+                left: Some(left.type_info()),
+                op,
+                right: right.type_info(),
+            }))
+        }
+        _ => Err(EvalError::new(EvalErrorKind::TypeMismatch {
+            left: left.type_info(),
             op,
-            right.type_info()
-        )),
+            right: right.type_info(),
+        })),
     }
 }
 
 fn eval_string_infix_expression(ls: &str, op: Op, rs: &str) -> Result<Object, EvalError> {
     match op {
-        Op::Add => Ok(Object::String(String::from(ls.to_owned() + rs))),
-        _ => Err(format!(
-            "unknown operator: {} {} {}",
-            String::from("STRING"),
+        Op::Add => Ok(Object::String(ls.to_owned() + rs)),
+        Op::Equals => Ok(Object::Boolean(ls == rs)),
+        Op::NotEquals => Ok(Object::Boolean(ls != rs)),
+        Op::LessThan => Ok(Object::Boolean(ls < rs)),
+        Op::GreaterThan => Ok(Object::Boolean(ls > rs)),
+        _ => Err(EvalError::new(EvalErrorKind::UnknownOperator {
+            left: Some(String::from("STRING")),
             op,
-            String::from("STRING")
-        )),
+            right: String::from("STRING"),
+        })),
     }
 }
 
@@ -215,22 +759,95 @@ fn eval_bool_infix_expression(lb: &bool, op: ast::Op, rb: &bool) -> Result<Objec
     match op {
         Op::Equals => Ok(Object::Boolean(lb == rb)),
         Op::NotEquals => Ok(Object::Boolean(lb != rb)),
-        _ => Err(format!("unknown operator: {} {} {}", lb, op, rb)),
+        _ => Err(EvalError::new(EvalErrorKind::UnknownOperator {
+            left: Some(lb.to_string()),
+            op,
+            right: rb.to_string(),
+        })),
+    }
+}
+
+fn eval_int_infix_expression(ln: i64, op: ast::Op, rn: i64) -> Result<Object, EvalError> {
+    let result = match op {
+        Op::Divide | Op::FloorDivide if rn == 0 => {
+            return Err(EvalError::new(EvalErrorKind::DivisionByZero))
+        }
+        Op::Modulo if rn == 0 => return Err(EvalError::new(EvalErrorKind::ModuloByZero)),
+        // `/` always divides exactly, `~/` is the integer division
+        Op::Divide => return eval_float_infix_expression(ln as f64, op, rn as f64),
+        Op::Power if rn < 0 => return eval_float_infix_expression(ln as f64, op, rn as f64),
+        Op::Add => ln.checked_add(rn),
+        Op::Subtract => ln.checked_sub(rn),
+        Op::Multiply => ln.checked_mul(rn),
+        Op::FloorDivide => floor_div(ln, rn),
+        Op::Modulo => Some(floor_mod(ln, rn)),
+        Op::Power => u32::try_from(rn).ok().and_then(|rn| ln.checked_pow(rn)),
+        Op::LessThan => return Ok(Object::Boolean(ln < rn)),
+        Op::GreaterThan => return Ok(Object::Boolean(ln > rn)),
+        Op::Equals => return Ok(Object::Boolean(ln == rn)),
+        Op::NotEquals => return Ok(Object::Boolean(ln != rn)),
+
+        _ => {
+            return Err(EvalError::new(EvalErrorKind::UnknownOperator {
+                left: Some(String::from("INT")),
+                op,
+                right: String::from("INT"),
+            }))
+        }
+    };
+
+    result.map(Object::Int).ok_or_else(|| {
+        EvalError::new(EvalErrorKind::IntegerOverflow {
+            left: Some(ln),
+            op,
+            right: rn,
+        })
+    })
+}
+
+/// Division rounding towards negative infinity, None when the result does not fit
+fn floor_div(ln: i64, rn: i64) -> Option<i64> {
+    let quotient = ln.checked_div(rn)?;
+    if ln.wrapping_rem(rn) != 0 && (ln < 0) != (rn < 0) {
+        quotient.checked_sub(1)
+    } else {
+        Some(quotient)
+    }
+}
+
+/// The remainder of the floor division, so it has the sign of the right side
+fn floor_mod(ln: i64, rn: i64) -> i64 {
+    let remainder = ln.wrapping_rem(rn);
+    if remainder != 0 && (remainder < 0) != (rn < 0) {
+        remainder + rn
+    } else {
+        remainder
     }
 }
 
-fn eval_int_infix_expression(ln: &f64, op: ast::Op, rn: &f64) -> Result<Object, EvalError> {
+fn eval_float_infix_expression(ln: f64, op: ast::Op, rn: f64) -> Result<Object, EvalError> {
     match op {
-        Op::Add => Ok(Object::Integer(ln + rn)),
-        Op::Subtract => Ok(Object::Integer(ln - rn)),
-        Op::Multiply => Ok(Object::Integer(ln * rn)),
-        Op::Divide => Ok(Object::Integer(ln / rn)),
+        Op::Add => Ok(Object::Float(ln + rn)),
+        Op::Subtract => Ok(Object::Float(ln - rn)),
+        Op::Multiply => Ok(Object::Float(ln * rn)),
+        Op::Divide | Op::FloorDivide if rn == 0.0 => {
+            Err(EvalError::new(EvalErrorKind::DivisionByZero))
+        }
+        Op::Modulo if rn == 0.0 => Err(EvalError::new(EvalErrorKind::ModuloByZero)),
+        Op::Divide => Ok(Object::Float(ln / rn)),
+        Op::FloorDivide => Ok(Object::Float((ln / rn).floor())),
+        Op::Modulo => Ok(Object::Float(ln - rn * (ln / rn).floor())),
+        Op::Power => Ok(Object::Float(ln.powf(rn))),
         Op::LessThan => Ok(Object::Boolean(ln < rn)),
         Op::GreaterThan => Ok(Object::Boolean(ln > rn)),
         Op::Equals => Ok(Object::Boolean(ln == rn)),
         Op::NotEquals => Ok(Object::Boolean(ln != rn)),
 
-        _ => Err(format!("unknown operator: {}", op)),
+        _ => Err(EvalError::new(EvalErrorKind::UnknownOperator {
+            left: Some(String::from("FLOAT")),
+            op,
+            right: String::from("FLOAT"),
+        })),
     }
 }
 
@@ -238,14 +855,29 @@ fn eval_prefix(op: ast::Op, right: Object) -> Result<Object, EvalError> {
     match op {
         ast::Op::Bang => eval_bang_prefix(right),
         ast::Op::Subtract => eval_sub_prefix(right),
-        _ => Err(format!("unknown operator: {}{}", op, right)),
+        _ => Err(EvalError::new(EvalErrorKind::UnknownOperator {
+            left: None,
+            op,
+            right: right.to_string(),
+        })),
     }
 }
 
 fn eval_sub_prefix(right: Object) -> Result<Object, EvalError> {
     match right {
-        Object::Integer(i) => Ok(Object::Integer(-i)),
-        _ => Err(format!("unknown operator: -{}", right)),
+        Object::Int(i) => i.checked_neg().map(Object::Int).ok_or_else(|| {
+            EvalError::new(EvalErrorKind::IntegerOverflow {
+                left: None,
+                op: Op::Subtract,
+                right: i,
+            })
+        }),
+        Object::Float(n) => Ok(Object::Float(-n)),
+        _ => Err(EvalError::new(EvalErrorKind::UnknownOperator {
+            left: None,
+            op: Op::Subtract,
+            right: right.to_string(),
+        })),
     }
 }
 
@@ -262,23 +894,29 @@ mod tests {
 
     use std::{cell::RefCell, rc::Rc};
 
-    use crate::{lexer, Parser};
+    use crate::{ast::Program, lexer, Parser};
 
     use super::*;
 
-    fn test_eval_string(test_case: &[(&str, &str)]) {
-        for (input, expected) in test_case {
-            let lexer = lexer::Lexer::new(String::from(*input));
-            let mut parser = Parser::new(lexer);
-            parser.read();
-            parser.read();
-            let program = parser.parse();
+    fn parse_program(input: &str) -> Program {
+        let lexer = lexer::Lexer::new(String::from(input));
+        let mut parser = Parser::new(lexer);
+        parser.read();
+        parser.read();
+        parser.parse().unwrap()
+    }
 
-            let ev: Env = Rc::new(RefCell::new(Default::default()));
+    /// Evaluates `input` in an environment of its own
+    fn eval_input(input: &str) -> Result<Object, EvalError> {
+        let ev: Env = Rc::new(RefCell::new(Default::default()));
+        eval(ast::Node::Program(parse_program(input)), &ev)
+    }
 
-            match eval(ast::Node::Program(program), &ev) {
+    fn test_eval_string(test_case: &[(&str, &str)]) {
+        for (input, expected) in test_case {
+            match eval_input(input) {
                 Ok(v) => p_assert_eq!(v.to_string(), *expected.to_string()),
-                Err(e) => p_assert_eq!(e, *expected.to_string()),
+                Err(e) => p_assert_eq!(e.to_string(), *expected.to_string()),
             }
         }
     }
@@ -300,122 +938,1007 @@ mod tests {
     }
 
     #[test]
-    fn evaluate_string_concatenation() {
-        let test_case = [("\"hello\" + \" \" + \"world\"", "hello world")];
+    fn evaluate_more_builtin_functions() {
+        let test_case = [
+            ("type(1)", "INT"),
+            ("type(\"a\")", "STRING"),
+            ("type(len)", "FUNCTION"),
+            ("str(12) + \"!\"", "12!"),
+            ("num(\" 42 \") + 1", "43"),
+            (
+                "num(\"abc\")",
+                "invalid argument to 'num': could not parse \"abc\" as a number",
+            ),
+            ("num(True)", "argument to 'num' not supported, got BOOLEAN"),
+            ("type()", "wrong number of arguments. got=0, want=1"),
+            ("input(1, 2)", "wrong number of arguments. got=2, want=0..1"),
+            ("print()", "null"),
+            ("let len = fn(x) { 42 } len(\"a\")", "42"),
+        ];
 
         test_eval_string(&test_case)
     }
 
     #[test]
-    fn evaluate_function_application() {
+    fn evaluate_arrays() {
         let test_case = [
-            ("let identity = fn(x) {x} identity(1)", "1"),
-            ("let identity = fn(x) {return x} identity(2)", "2"),
-            ("let double = fn(x) { x * 2 } double(5)", "10"),
-            ("let add = fn(x, y) { x + y } add(5, 10)", "15"),
-            ("let add = fn(x, y) { x + y } add(5 + 5, add(5, 5))", "20"),
-            ("fn(x) {x}(5)", "5"),
+            ("[1, 2 * 2, \"three\"]", "[1, 4, \"three\"]"),
+            ("[]", "[]"),
+            ("[1, 2, 3][0]", "1"),
+            ("[1, 2, 3][1 + 1]", "3"),
+            ("let a = [1, 2, 3] a[-1]", "3"),
+            ("let a = [1, 2, 3] a[-3]", "1"),
+            ("[[1, 2], [3]][0][1]", "2"),
+            ("[1, 2, 3][3]", "index out of bounds: index 3, length 3"),
+            ("[1, 2, 3][-4]", "index out of bounds: index -4, length 3"),
+            ("[1, 2, 3][0.5]", "index must be an integer, got 0.5"),
+            ("[1][\"a\"]", "index operator not supported: ARRAY[STRING]"),
+            ("[1] + [2]", "unknown operator: ARRAY + ARRAY"),
+            ("type([])", "ARRAY"),
         ];
 
         test_eval_string(&test_case)
     }
 
     #[test]
-    fn evaluate_function() {
-        let test_case = [(" fn(x) {x + 2}", "fn(x) { x+2 }")];
+    fn evaluate_array_builtins() {
+        let test_case = [
+            ("len([1, 2, 3])", "3"),
+            ("let a = [1] let b = push(a, 2); [a, b]", "[[1], [1, 2]]"),
+            ("first([1, 2])", "1"),
+            ("first([])", "null"),
+            ("last([1, 2])", "2"),
+            ("last([])", "null"),
+            ("rest([1, 2, 3])", "[2, 3]"),
+            ("rest([])", "[]"),
+            ("slice([1, 2, 3, 4], 1)", "[2, 3, 4]"),
+            ("slice([1, 2, 3, 4], 1, 3)", "[2, 3]"),
+            ("slice([1, 2, 3, 4], -2)", "[3, 4]"),
+            ("slice([1, 2, 3, 4], 3, 1)", "[]"),
+            ("slice([1, 2, 3, 4], 0, 10)", "[1, 2, 3, 4]"),
+            ("concat([1], [], [2, 3])", "[1, 2, 3]"),
+            ("concat()", "[]"),
+            ("push(1, 2)", "argument to 'push' not supported, got INT"),
+            (
+                "concat([1], 2)",
+                "argument to 'concat' not supported, got INT",
+            ),
+            ("slice([1])", "wrong number of arguments. got=1, want=2..3"),
+            (
+                "slice([1], 1, 2, 3)",
+                "wrong number of arguments. got=4, want=2..3",
+            ),
+        ];
 
         test_eval_string(&test_case)
     }
 
     #[test]
-    fn evaluate_let() {
+    fn evaluate_hashes() {
         let test_case = [
-            ("let a = 5 a", "5"),
-            ("let a = 5 * 5 a", "25"),
-            ("let a = 5 let b = a b", "5"),
-            ("let a = 5 let b = a let c = a + b + 5 c", "15"),
+            (
+                "{\"b\": 2, \"a\": 1 + 1, 3: \"three\", True: [1]}",
+                "{true: [1], 3: \"three\", \"a\": 2, \"b\": 2}",
+            ),
+            ("{}", "{}"),
+            ("{\"a\": 1}[\"a\"]", "1"),
+            ("{\"a\": 1}[\"b\"]", "null"),
+            ("let key = \"k\" {key: 5}[\"k\"]", "5"),
+            ("{1: \"one\"}[1.0]", "one"),
+            ("{False: 0}[1 > 2]", "0"),
+            ("{\"a\": 1, \"a\": 2}", "{\"a\": 2}"),
+            ("{[1]: 2}", "unusable as hash key: ARRAY"),
+            ("{}[fn(x) { x }]", "unusable as hash key: FUNCTION"),
+            ("type({})", "HASH"),
         ];
 
         test_eval_string(&test_case)
     }
 
     #[test]
-    fn error_handling() {
+    fn evaluate_index_assignment() {
         let test_case = [
-            ("5 + True", "type mismatch: INT + BOOLEAN"),
-            ("5 + True; 5;", "type mismatch: INT + BOOLEAN"),
-            ("-True", "unknown operator: -true"),
-            ("True + False", "unknown operator: true + false"),
-            ("5 True + False 5", "unknown operator: true + false"),
+            ("let h = {} h[\"a\"] = 1; h", "{\"a\": 1}"),
+            ("let h = {\"a\": 1} h[\"a\"] = h[\"a\"] + 1; h[\"a\"]", "2"),
+            ("let h = {} h[\"a\"] = 5", "5"),
+            ("let a = [1, 2, 3] a[-1] = 4; a", "[1, 2, 4]"),
             (
-                "\"hello\" - \" \" - \"world\"",
-                "unknown operator: STRING - STRING",
+                "let h = {\"xs\": [1, 2]} h[\"xs\"][0] = 9; h",
+                "{\"xs\": [9, 2]}",
             ),
             (
-                "if (10 > 5) { True + False }",
-                "unknown operator: true + false",
+                "let a = [] let b = a a[0] = 1",
+                "index out of bounds: index 0, length 0",
             ),
-            ("foobar", "identifier not found: foobar"),
+            ("let a = [1] let b = a; b[0] = 2; a", "[1]"),
+            (
+                "let s = \"abc\" s[0] = \"x\"",
+                "index operator not supported: STRING[INT]",
+            ),
+            ("nope[0] = 1", "identifier not found: nope"),
         ];
 
         test_eval_string(&test_case)
     }
 
     #[test]
-    fn evaluate_return() {
+    fn evaluate_hash_builtins() {
         let test_case = [
-            ("return 1;", "1"),
-            ("return 2; 9:", "2"),
-            ("return 1 * 3; 9:", "3"),
-            ("9 return 1 * 4; 9:", "4"),
+            ("len({\"a\": 1, \"b\": 2})", "2"),
+            ("keys({\"b\": 1, \"a\": 2})", "[\"a\", \"b\"]"),
+            ("values({\"b\": 1, \"a\": 2})", "[2, 1]"),
+            ("has({\"a\": 1}, \"a\")", "true"),
+            ("has({\"a\": 1}, \"b\")", "false"),
             (
-                "if (10 > 1) {
-                    if (10 > 1) {
-                        return 10
-                    }
-                    return 1
-                }",
-                "10",
+                "let h = {\"a\": 1, \"b\": 2}; let g = delete(h, \"a\"); [h, g]",
+                "[{\"a\": 1, \"b\": 2}, {\"b\": 2}]",
             ),
+            ("delete({}, \"a\")", "{}"),
+            ("has({}, [])", "unusable as hash key: ARRAY"),
+            ("keys([])", "argument to 'keys' not supported, got ARRAY"),
         ];
 
         test_eval_string(&test_case)
     }
 
     #[test]
-    fn evaluate_ifelse() {
+    fn evaluate_assignment() {
         let test_case = [
-            ("if ( True ) { 1 }", "1"),
-            ("if ( False ) { 2 }", "null"),
-            ("if (1) { 3 }", "3"),
-            ("if (1 < 2) { 4 }", "4"),
-            ("if (1 > 2) { 5 }", "null"),
-            ("if (1 > 2) { 6 } else { 7 }", "7"),
-            ("if (1 < 2) { 8 } else { 9 }", "8"),
+            ("let x = 1 x = 2; x", "2"),
+            ("let x = 1 x = 2", "2"),
+            ("let x = 1 x = x + 1; x", "2"),
+            ("let x = 5 x += 2; x -= 1; x *= 3; x /= 2; x", "9.0"),
+            ("let a = 1 let b = 2 a = b = 7; [a, b]", "[7, 7]"),
+            ("let s = \"a\" s += \"b\"; s", "ab"),
+            ("let h = {\"n\": 1} h[\"n\"] *= 5; h", "{\"n\": 5}"),
+            ("let x = 1 let f = fn() { x = 5 } f() x", "5"),
+            (
+                "let i = 0 let total = 0 while (i < 5) { total += i; i += 1 } total",
+                "10",
+            ),
+            ("y = 1", "cannot assign to undeclared variable: y"),
+            ("y += 1", "identifier not found: y"),
+            ("let x = True x += 1", "type mismatch: BOOLEAN + INT"),
+            // The index is evaluated once, for reading the element and for storing it
+            (
+                "let n = 0 let a = [10, 20, 30] fn i() { n += 1; n - 1 } a[i()] += 1; [a, n]",
+                "[[11, 20, 30], 1]",
+            ),
+            (
+                "let n = 0 let a = [[1, 2], [3, 4]] fn i() { n += 1; n - 1 } a[i()][1] = 9; [a, n]",
+                "[[[1, 9], [3, 4]], 1]",
+            ),
+            ("let a = [1] a[0] += 1", "2"),
         ];
 
         test_eval_string(&test_case)
     }
 
     #[test]
-    fn evaluate_prefix() {
+    fn evaluate_const() {
         let test_case = [
-            ("!True ", "false"),
-            ("!False ", "true"),
-            ("!5 ", "false"),
-            ("!!True ", "true"),
-            ("!!False ", "false"),
-            ("!!5 ", "true"),
+            ("const x = 5 x", "5"),
+            ("const x = 5 let y = x y = 6; [x, y]", "[5, 6]"),
+            ("const x = 1 let x = 2", "cannot assign to constant: x"),
+            ("const x = 1 const x = 2", "cannot assign to constant: x"),
+            ("let x = 1 const x = 2 x", "2"),
+            ("const c = 1 for c in [2] { c = 3 } c", "1"),
+            ("const x = 1 let f = fn(x) { x = 2; x } f(1)", "2"),
         ];
 
         test_eval_string(&test_case)
     }
 
     #[test]
-    fn evaluate_boolean() {
-        let test_case = [
-            ("True ", "true"),
-            ("False ", "false"),
+    fn const_assignment_across_programs() {
+        let ev: Env = Rc::new(RefCell::new(Default::default()));
+
+        for (input, expected) in [
+            ("const limit = 10", Ok(Object::Nil)),
+            (
+                "limit = 11",
+                Err(EvalErrorKind::ConstAssignment {
+                    name: "limit".to_string(),
+                }),
+            ),
+            ("limit", Ok(Object::Int(10))),
+        ] {
+            p_assert_eq!(
+                eval(ast::Node::Program(parse_program(input)), &ev).map_err(|e| e.kind),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn evaluate_logical_operators() {
+        let test_case = [
+            ("True && True", "true"),
+            ("True && False", "false"),
+            ("False || True", "true"),
+            ("False or False", "false"),
+            ("1 < 2 and 2 < 3", "true"),
+            ("1 && 2", "2"),
+            ("if (False) { 1 } && 2", "null"),
+            ("0 || \"default\"", "0"),
+            ("False || \"default\"", "default"),
+            ("True || False && False", "true"),
+            ("(True || False) && False", "false"),
+            ("!True && False", "false"),
+            ("!False || False", "true"),
+            ("False && missing", "false"),
+            ("True || missing()", "true"),
+            ("True && missing", "identifier not found: missing"),
+            (
+                "let hit = False let f = fn() { hit = True } False && f(); hit",
+                "false",
+            ),
+            (
+                "let hit = False let f = fn() { hit = True } True && f(); hit",
+                "true",
+            ),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_numeric_types() {
+        let test_case = [
+            ("9007199254740993", "9007199254740993"),
+            ("9007199254740992 + 1", "9007199254740993"),
+            ("type(1)", "INT"),
+            ("type(1.5)", "FLOAT"),
+            ("type(1e3)", "FLOAT"),
+            ("1 + 1.5", "2.5"),
+            ("1.5 * 2", "3.0"),
+            ("1 == 1.0", "true"),
+            ("2 > 1.5", "true"),
+            ("0xff", "255"),
+            ("0b1010", "10"),
+            ("1_000_000", "1000000"),
+            ("1e3", "1000.0"),
+            ("2.5e-1", "0.25"),
+            ("1E+2", "100.0"),
+            (
+                "9223372036854775807 + 1",
+                "integer overflow: 9223372036854775807 + 1",
+            ),
+            (
+                "-9223372036854775807 - 2",
+                "integer overflow: -9223372036854775807 - 2",
+            ),
+            (
+                "4294967296 * 4294967296",
+                "integer overflow: 4294967296 * 4294967296",
+            ),
+            ("2 ** 63", "integer overflow: 2 ** 63"),
+            ("2 ** 62", "4611686018427387904"),
+            (
+                "-(-9223372036854775807 - 1)",
+                "integer overflow: --9223372036854775808",
+            ),
+            ("1.0 / 0", "division by zero"),
+            ("{1: \"a\"}[1.0]", "a"),
+            ("{1.5: \"a\"}[1.5]", "a"),
+            ("[1, 2][1.0]", "index must be an integer, got 1.0"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_strings() {
+        let test_case = [
+            (r#"let name = "Ada"; "Hello ${name}""#, "Hello Ada"),
+            (
+                r#""${1 + 2} is ${1.5 * 2} and ${[1, "a"]}""#,
+                "3 is 3.0 and [1, \"a\"]",
+            ),
+            (
+                r#"let greet = fn(n) { "hi ${n}" }; "${greet("${"x"}")}!""#,
+                "hi x!",
+            ),
+            (r#""${ {"a": 1}["a"] }""#, "1"),
+            (r#"len("a\tb\n\u{e9}\\")"#, "6"),
+            (r#""\${name}""#, "${name}"),
+            (r#""${missing}""#, "identifier not found: missing"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_string_operators() {
+        let test_case = [
+            (r#""abc" == "abc""#, "true"),
+            (r#""abc" != "abd""#, "true"),
+            (r#""abc" < "abd""#, "true"),
+            (r#""b" > "abc""#, "true"),
+            (r#""ab" * 3"#, "ababab"),
+            (r#"2 * "-""#, "--"),
+            (r#""ab" * -1"#, ""),
+            (
+                r#""ab" * 9223372036854775807"#,
+                "string too long: more than 1073741824 bytes",
+            ),
+            (
+                r#""a" * 9223372036854775807"#,
+                "string too long: more than 1073741824 bytes",
+            ),
+            (
+                r#"1073741825 * "a""#,
+                "string too long: more than 1073741824 bytes",
+            ),
+            (r#""héllo"[1]"#, "é"),
+            (r#""héllo"[-1]"#, "o"),
+            (r#""abc"[3]"#, "index out of bounds: index 3, length 3"),
+            (r#""abc"[0.5]"#, "index must be an integer, got 0.5"),
+            (r#""ab" - "b""#, "unknown operator: STRING - STRING"),
+            (r#""ab" * 1.5"#, "type mismatch: STRING * FLOAT"),
+            (r#"slice("héllo", 1, 3)"#, "él"),
+            (r#"slice("héllo", -2)"#, "lo"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_string_builtins() {
+        let test_case = [
+            (r#"split("a,b,,c", ",")"#, r#"["a", "b", "", "c"]"#),
+            (r#"split("  a b\tc ")"#, r#"["a", "b", "c"]"#),
+            (
+                r#"split("abc", "")"#,
+                "invalid argument to 'split': separator is empty",
+            ),
+            (
+                r#"split("a b", " ", 1)"#,
+                "wrong number of arguments. got=3, want=1..2",
+            ),
+            (r#"join(["a", 1, 2.5], ", ")"#, "a, 1, 2.5"),
+            ("join()", "wrong number of arguments. got=0, want=1..2"),
+            (r#"join(chars("abc"))"#, "abc"),
+            (
+                r#"join("abc", ",")"#,
+                "argument to 'join' not supported, got STRING",
+            ),
+            (r#"trim("  hi \n")"#, "hi"),
+            (r#"upper("straße")"#, "STRASSE"),
+            (r#"lower("ÀB")"#, "àb"),
+            (r#"replace("a-b-c", "-", "+")"#, "a+b+c"),
+            (r#"contains("haystack", "st")"#, "true"),
+            (
+                r#"contains("haystack", 1)"#,
+                "argument to 'contains' not supported, got INT",
+            ),
+            (r#"starts_with("prefix", "pre")"#, "true"),
+            (r#"ends_with("suffix", "pre")"#, "false"),
+            (r#"find("héllo", "l")"#, "2"),
+            (r#"find("hello", "z")"#, "null"),
+            (r#"chars("hé")"#, r#"["h", "é"]"#),
+            (r#"format("{} + {} = {}", 1, 2, "three")"#, "1 + 2 = three"),
+            (r#"format("{{{}}}", [1])"#, "{[1]}"),
+            (
+                "format()",
+                "wrong number of arguments. got=0, want=1 or more",
+            ),
+            (
+                r#"format("{} {}", 1)"#,
+                "invalid argument to 'format': more placeholders than values",
+            ),
+            (
+                r#"format("{}", 1, 2)"#,
+                "invalid argument to 'format': more values than placeholders",
+            ),
+            (
+                r#"format("{", 1)"#,
+                "invalid argument to 'format': unmatched `{` in template",
+            ),
+            (r#"upper(1)"#, "argument to 'upper' not supported, got INT"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_conversions() {
+        let test_case = [
+            ("int(3.9)", "3"),
+            ("int(-3.9)", "-3"),
+            ("int(\" 42 \")", "42"),
+            ("int(True)", "1"),
+            (
+                "int(\"4.2\")",
+                "invalid argument to 'int': could not parse \"4.2\" as an integer",
+            ),
+            (
+                "int(1e300)",
+                "invalid argument to 'int': 1e300 does not fit in an integer",
+            ),
+            ("int([])", "argument to 'int' not supported, got ARRAY"),
+            ("float(3)", "3.0"),
+            ("float(\"2.5\")", "2.5"),
+            ("float(False)", "0.0"),
+            (
+                "float(\"x\")",
+                "invalid argument to 'float': could not parse \"x\" as a number",
+            ),
+            ("num(\"12\")", "12"),
+            ("num(\"1.5\")", "1.5"),
+            ("type(num(\"12\"))", "INT"),
+            ("str(2.0)", "2.0"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_loops() {
+        let test_case = [
+            ("let c = [0] while (c[0] < 5) { c[0] = c[0] + 1 } c[0]", "5"),
+            ("while (False) { 1 }", "null"),
+            (
+                "let c = [0] while (True) { c[0] = c[0] + 1 if (c[0] == 3) { break } } c[0]",
+                "3",
+            ),
+            (
+                "let s = [0] for x in [1, 2, 3, 4, 5] { if (x == 2) { continue } s[0] = s[0] + x } s[0]",
+                "13",
+            ),
+            (
+                "let out = [\"\"] for c in \"abc\" { out[0] = c + out[0] } out[0]",
+                "cba",
+            ),
+            (
+                "let out = [\"\"] for k in {\"b\": 1, \"a\": 2} { out[0] = out[0] + k } out[0]",
+                "ab",
+            ),
+            (
+                "let f = fn(xs) { for x in xs { if (x > 2) { return x } } return 0 } f([1, 5, 3])",
+                "5",
+            ),
+            (
+                "let f = fn() { while (True) { return 7 } } f()",
+                "7",
+            ),
+            (
+                "let s = [0] for i in [1, 2] { for j in [10, 20] { if (j == 20) { break } s[0] = s[0] + i * j } } s[0]",
+                "30",
+            ),
+            (
+                "let n = 0 for x in [1, 2, 3] { let r = if (x == 2) { break } else { x } n += 1 } n",
+                "1",
+            ),
+            (
+                "let out = [] for x in [1, 2, 3] { out = push(out, [if (x == 1) { continue } else { x }]) } out",
+                "[[2], [3]]",
+            ),
+            (
+                "let n = 0 while (True) { n += 1 let r = if (n == 3) { break } else { n } } n",
+                "3",
+            ),
+            (
+                "let s = 0 for x in [1, 2, 3] { s = s + [if (x == 2) { continue } else { x }][0] } s",
+                "4",
+            ),
+            (
+                "let f = fn(xs) { for x in xs { let h = {\"k\": if (x > 1) { return x } else { 0 }} } 0 } f([1, 5])",
+                "5",
+            ),
+            (
+                "let f = fn(a, b = if (a) { return \"early\" } else { 2 }) { b }; [f(True), f(False)]",
+                "[\"early\", 2]",
+            ),
+            ("for x in 5 { x }", "not iterable: INT"),
+            ("for x in [] { x } 1", "1"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_block_scopes() {
+        let test_case = [
+            ("if (True) { let a = 1 } a", "identifier not found: a"),
+            (
+                "if (False) { 1 } else { let b = 2 } b",
+                "identifier not found: b",
+            ),
+            (
+                "while (True) { let c = 3 break } c",
+                "identifier not found: c",
+            ),
+            ("for x in [1] { let y = x } y", "identifier not found: y"),
+            ("for x in [1, 2] { x } x", "identifier not found: x"),
+            ("let a = 1 if (True) { let a = 2 a = 3 } a", "1"),
+            ("const a = 1 if (True) { let a = 2 } a", "1"),
+            ("let a = 1 if (True) { a = 2 } a", "2"),
+            ("let x = 0 for x in [1, 2] { x } x", "0"),
+            ("if (True) { let a = 1 if (True) { let a = 2 } a }", "1"),
+            ("if (True) { fn f() { 1 } } f()", "identifier not found: f"),
+            (
+                "let fs = [] for i in [1, 2, 3] { fs = push(fs, fn() { i }) } [fs[0](), fs[2]()]",
+                "[1, 3]",
+            ),
+            (
+                "let make = fn() { if (True) { let n = 5 fn() { n } } } make()()",
+                "5",
+            ),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_string_concatenation() {
+        let test_case = [("\"hello\" + \" \" + \"world\"", "hello world")];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_function_application() {
+        let test_case = [
+            ("let identity = fn(x) {x} identity(1)", "1"),
+            ("let identity = fn(x) {return x} identity(2)", "2"),
+            ("let double = fn(x) { x * 2 } double(5)", "10"),
+            ("let add = fn(x, y) { x + y } add(5, 10)", "15"),
+            ("let add = fn(x, y) { x + y } add(5 + 5, add(5, 5))", "20"),
+            ("fn(x) {x}(5)", "5"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_closures() {
+        let test_case = [
+            (
+                "let make_adder = fn(n) { fn(x) { x + n } }; let add2 = make_adder(2); add2(3)",
+                "5",
+            ),
+            (
+                "let make_adder = fn(n) { fn(x) { x + n } }; let a = make_adder(1); let b = make_adder(10); a(1) + b(1)",
+                "13",
+            ),
+            ("let f = fn(x) { fn(y) { fn(z) { x + y + z } } }; f(1)(2)(3)", "6"),
+            (
+                "let counter = fn() { let n = 0; fn() { n += 1 } }; let c = counter(); c(); c(); c()",
+                "3",
+            ),
+            (
+                "let counter = fn() { let n = 0; fn() { n += 1 } }; let a = counter(); let b = counter(); a(); a(); b()",
+                "1",
+            ),
+            ("let x = 1; let f = fn() { x }; let g = fn(x) { f() }; g(2)", "1"),
+            ("let x = 1; let f = fn() { x }; x = 5; f()", "5"),
+            (
+                "let f = fn() { y }; let g = fn(y) { f() }; g(1)",
+                "identifier not found: y",
+            ),
+            (
+                "let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(5)",
+                "120",
+            ),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_scopes() {
+        let test_case = [
+            ("let a = 1; let f = fn() { a + 1 }; f()", "2"),
+            (
+                "let a = 1; let f = fn() { fn() { if (True) { a * 10 } } }; f()()",
+                "10",
+            ),
+            ("let a = 1; let f = fn(a) { a }; f(2) + a", "3"),
+            (
+                "let a = 1; let f = fn() { let a = 2; a }; f() * 10 + a",
+                "21",
+            ),
+            ("let a = 1; let f = fn() { a = 2 }; f(); a", "2"),
+            (
+                "let f = fn() { let local = 1 }; f(); local",
+                "identifier not found: local",
+            ),
+            ("let len = fn(x) { 42 }; len(\"abc\")", "42"),
+            (
+                "let f = fn() { b = 1 }; f()",
+                "cannot assign to undeclared variable: b",
+            ),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_function_declarations() {
+        let test_case = [
+            ("fn add(a, b) { a + b } add(1, 2)", "3"),
+            ("let x = double(4); fn double(n) { n * 2 } x", "8"),
+            (
+                "fn is_even(n) { if (n == 0) { True } else { is_odd(n - 1) } }
+                fn is_odd(n) { if (n == 0) { False } else { is_even(n - 1) } }
+                is_even(10)",
+                "true",
+            ),
+            (
+                "fn outer() { return inner(); fn inner() { 5 } } outer()",
+                "5",
+            ),
+            (
+                "fn make() { fn counter() { 1 } counter } make()",
+                "fn counter() { 1 }",
+            ),
+            ("fn f() { 1 } let g = f; g", "fn f() { 1 }"),
+            ("fn f() { 1 } f = 2; f", "2"),
+            // A function in an inner scope can shadow a constant
+            ("const x = 1 fn f() { fn x() { 2 } x() } [f(), x]", "[2, 1]"),
+            (
+                "fn f() { inner() } f(); inner",
+                "identifier not found: inner",
+            ),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn function_names_in_traces() {
+        let input = "fn fail(x) { x + True }
+let alias = fail
+alias(1)";
+        let error = eval_input(input).unwrap_err();
+
+        let trace: Vec<(String, usize, usize)> = error
+            .trace
+            .iter()
+            .map(|f| (f.function.clone(), f.span.line, f.span.column))
+            .collect();
+        p_assert_eq!(trace, vec![("fail".to_string(), 3, 1)]);
+    }
+
+    #[test]
+    fn evaluate_parameters() {
+        let test_case = [
+            (
+                "fn add(a, b) { a + b } add(1)",
+                "wrong number of arguments. got=1, want=2",
+            ),
+            (
+                "fn add(a, b) { a + b } add(1, 2, 3)",
+                "wrong number of arguments. got=3, want=2",
+            ),
+            ("fn(x) { x }()", "wrong number of arguments. got=0, want=1"),
+            ("fn inc(a, b = 1) { a + b } inc(1)", "2"),
+            ("fn inc(a, b = 1) { a + b } inc(1, 5)", "6"),
+            (
+                "fn inc(a, b = 1) { a + b } inc()",
+                "wrong number of arguments. got=0, want=1..2",
+            ),
+            (
+                "fn inc(a, b = 1) { a + b } inc(1, 2, 3)",
+                "wrong number of arguments. got=3, want=1..2",
+            ),
+            (
+                "fn f(a, b = a * 2, c = a + b) { [a, b, c] } f(1)",
+                "[1, 2, 3]",
+            ),
+            ("let n = 10; fn f(a = n) { a } n = 20; f()", "20"),
+            ("fn f(a = missing) { a } f(1)", "1"),
+            (
+                "fn f(a = missing) { a } f()",
+                "identifier not found: missing",
+            ),
+            (
+                "fn f(first, ...rest) { [first, rest] } f(1, 2, 3)",
+                "[1, [2, 3]]",
+            ),
+            ("fn f(first, ...rest) { rest } f(1)", "[]"),
+            (
+                "fn f(first, ...rest) { rest } f()",
+                "wrong number of arguments. got=0, want=1 or more",
+            ),
+            (
+                "fn f(a, b = 2, ...rest) { [a, b, rest] } f(1)",
+                "[1, 2, []]",
+            ),
+            (
+                "fn f(a, b = 2, ...rest) { [a, b, rest] } f(1, 3, 4, 5)",
+                "[1, 3, [4, 5]]",
+            ),
+            (
+                "fn sum(...xs) { let t = 0; for x in xs { t += x } t } sum()",
+                "0",
+            ),
+            (
+                "fn sum(...xs) { let t = 0; for x in xs { t += x } t } sum(1, 2, 3)",
+                "6",
+            ),
+            ("fn(a, b = 2, ...c) { a }", "fn(a, b = 2, ...c) { a }"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_named_arguments() {
+        let connect = "fn connect(host, port = 80, tls = False) { [host, port, tls] } ";
+        let test_case = [
+            ("connect(host: \"x\", port: 8080)", "[\"x\", 8080, false]"),
+            ("connect(\"x\", tls: True)", "[\"x\", 80, true]"),
+            ("connect(tls: True, host: \"x\")", "[\"x\", 80, true]"),
+            (
+                "connect(\"x\", hots: \"y\")",
+                "unknown argument `hots` in call to `connect`",
+            ),
+            (
+                "connect(\"x\", host: \"y\")",
+                "argument `host` is given more than once in call to `connect`",
+            ),
+            (
+                "connect(\"x\", tls: True, tls: False)",
+                "argument `tls` is given more than once in call to `connect`",
+            ),
+            (
+                "connect(port: 1)",
+                "missing argument `host` in call to `connect`",
+            ),
+            (
+                "connect(\"x\", 1, True, 2, tls: False)",
+                "wrong number of arguments. got=4, want=1..3",
+            ),
+        ];
+        for (call, expected) in test_case {
+            test_eval_string(&[(&format!("{}{}", connect, call), expected)]);
+        }
+
+        let test_case = [
+            (
+                "fn f(a, ...rest) { [a, rest] } f(1, 2, a: 3)",
+                "argument `a` is given more than once in call to `f`",
+            ),
+            (
+                "fn f(a, ...rest) { [a, rest] } f(rest: 3)",
+                "unknown argument `rest` in call to `f`",
+            ),
+            ("let f = fn(a, b = a) { [a, b] }; f(b: 2, a: 1)", "[1, 2]"),
+            ("len(x: \"abc\")", "unknown argument `x` in call to `len`"),
+            ("let x = 1; fn f(x) { x } f(x: x + 1)", "2"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_function() {
+        let test_case = [(" fn(x) {x + 2}", "fn(x) { x+2 }")];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_let() {
+        let test_case = [
+            ("let a = 5 a", "5"),
+            ("let a = 5 * 5 a", "25"),
+            ("let a = 5 let b = a b", "5"),
+            ("let a = 5 let b = a let c = a + b + 5 c", "15"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn error_handling() {
+        let test_case = [
+            ("5 + True", "type mismatch: INT + BOOLEAN"),
+            ("5 + True; 5;", "type mismatch: INT + BOOLEAN"),
+            ("-True", "unknown operator: -true"),
+            ("True + False", "unknown operator: true + false"),
+            ("5 True + False 5", "unknown operator: true + false"),
+            (
+                "\"hello\" - \" \" - \"world\"",
+                "unknown operator: STRING - STRING",
+            ),
+            (
+                "if (10 > 5) { True + False }",
+                "unknown operator: true + false",
+            ),
+            ("foobar", "identifier not found: foobar"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn error_kinds() {
+        let test_case = [
+            (
+                "5 + True",
+                EvalErrorKind::TypeMismatch {
+                    left: "INT".to_string(),
+                    op: Op::Add,
+                    right: "BOOLEAN".to_string(),
+                },
+            ),
+            (
+                "foobar",
+                EvalErrorKind::UndefinedVariable {
+                    name: "foobar".to_string(),
+                },
+            ),
+            (
+                "let f = fn(a, b) { a } f(1)",
+                EvalErrorKind::ArityMismatch { got: 1, want: 2 },
+            ),
+            (
+                "let x = 5 x(1)",
+                EvalErrorKind::NotCallable {
+                    found: "INT".to_string(),
+                },
+            ),
+        ];
+
+        for (input, expected) in test_case {
+            p_assert_eq!(eval_input(input).unwrap_err().kind, expected);
+        }
+    }
+
+    #[test]
+    fn error_span_and_trace() {
+        let input = "let inner = fn(x) { x + True }
+let outer = fn(g, x) { g(x) }
+outer(inner, 1)";
+        let error = eval_input(input).unwrap_err();
+
+        let span = error.span.unwrap();
+        p_assert_eq!((span.line, span.column), (1, 23));
+
+        let trace: Vec<(String, usize, usize)> = error
+            .trace
+            .iter()
+            .map(|f| (f.function.clone(), f.span.line, f.span.column))
+            .collect();
+        p_assert_eq!(
+            trace,
+            vec![("g".to_string(), 2, 24), ("outer".to_string(), 3, 1)]
+        );
+    }
+
+    #[test]
+    fn evaluate_call_depth_limit() {
+        // The limit is per thread, running on a thread of our own keeps it from other tests and
+        // gives the recursion enough stack
+        let interpreter = std::thread::Builder::new()
+            .stack_size(256 * 1024 * 1024)
+            .spawn(|| {
+                set_max_call_depth(100);
+
+                let test_case = [
+                    (
+                        "let f = fn(n) { f(n + 1) }; f(0)",
+                        "stack overflow: more than 100 nested calls",
+                    ),
+                    // The depth goes back to zero when the error unwinds the calls
+                    (
+                        "let f = fn(n) { if (n < 1) { 0 } else { 1 + f(n - 1) } }; f(99)",
+                        "99",
+                    ),
+                    (
+                        "let f = fn(n) { if (n < 1) { 0 } else { 1 + f(n - 1) } }; f(100)",
+                        "stack overflow: more than 100 nested calls",
+                    ),
+                ];
+                test_eval_string(&test_case);
+
+                let error = eval_input("let f = fn() { f() }\nf()").unwrap_err();
+
+                p_assert_eq!(error.trace.len(), 100);
+                let last = error.trace.last().unwrap();
+                p_assert_eq!((last.span.line, last.span.column), (2, 1));
+            })
+            .unwrap();
+
+        interpreter.join().unwrap();
+    }
+
+    #[test]
+    fn evaluate_stack_limit() {
+        let interpreter = std::thread::Builder::new()
+            .stack_size(16 * 1024 * 1024)
+            .spawn(|| {
+                set_stack_size(16 * 1024 * 1024);
+
+                // Every call is nested in several expressions, so the stack runs out well before
+                // the call depth limit
+                let input = r#"fn f(n) {
+                    if (n < 1) { 0 } else {
+                        let a = {"k": if (True) { if (True) { if (True) { [f(n-1)][0] + 1 } } } }
+                        a["k"]
+                    }
+                }
+                f(1999)"#;
+                let error = eval_input(input).unwrap_err();
+
+                assert!(
+                    error
+                        .to_string()
+                        .starts_with("stack overflow: out of stack after"),
+                    "{}",
+                    error
+                );
+                p_assert_eq!(error.span.map(|span| span.line), Some(3));
+                p_assert_eq!(
+                    eval_input(&input.replace("1999", "10"))
+                        .unwrap()
+                        .to_string(),
+                    "10"
+                );
+            })
+            .unwrap();
+
+        interpreter.join().unwrap();
+    }
+
+    #[test]
+    fn evaluate_return() {
+        let test_case = [
+            ("return 1;", "1"),
+            ("return 2; 9;", "2"),
+            ("return 1 * 3; 9;", "3"),
+            ("9 return 1 * 4; 9;", "4"),
+            (
+                "if (10 > 1) {
+                    if (10 > 1) {
+                        return 10
+                    }
+                    return 1
+                }",
+                "10",
+            ),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_ifelse() {
+        let test_case = [
+            ("if ( True ) { 1 }", "1"),
+            ("if ( False ) { 2 }", "null"),
+            ("if (1) { 3 }", "3"),
+            ("if (1 < 2) { 4 }", "4"),
+            ("if (1 > 2) { 5 }", "null"),
+            ("if (1 > 2) { 6 } else { 7 }", "7"),
+            ("if (1 < 2) { 8 } else { 9 }", "8"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_prefix() {
+        let test_case = [
+            ("!True ", "false"),
+            ("!False ", "true"),
+            ("!5 ", "false"),
+            ("!!True ", "true"),
+            ("!!False ", "false"),
+            ("!!5 ", "true"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_boolean() {
+        let test_case = [
+            ("True ", "true"),
+            ("False ", "false"),
             ("1 < 2", "true"),
             ("1 > 2", "false"),
             ("1 < 1", "false"),
@@ -442,17 +1965,45 @@ mod tests {
     fn evaluate_int() {
         let test_case = [
             ("5 ", "5"),
-            ("231.00", "231"),
+            ("231.00", "231.0"),
             ("-5 ", "-5"),
-            ("-231.00", "-231"),
+            ("-231.00", "-231.0"),
             ("5 + 5 + 5 + 5 - 10", "10"),
             ("2 * 2 * 2 * 2 * 2", "32"),
             ("20 + 2 * -10", "0"),
-            ("50 / 2 * 2 + 10", "60"),
+            ("50 / 2 * 2 + 10", "60.0"),
             ("3 * (3 * 3) + 10", "37"),
-            ("(5 + 10 * 2 + 15 / 3) * 2 + -10", "50"),
-            // TODO: Fix parser bug - This does not get parsed correcrly.
-            // ("-50 + 100 + -50", "0"),
+            ("(5 + 10 * 2 + 15 / 3) * 2 + -10", "50.0"),
+            ("-50 + 100 + -50", "0"),
+            ("-5 * 2 + 20", "10"),
+        ];
+
+        test_eval_string(&test_case)
+    }
+
+    #[test]
+    fn evaluate_arithmetic_operators() {
+        let test_case = [
+            ("7 % 3", "1"),
+            ("-7 % 3", "2"),
+            ("7 % -3", "-2"),
+            ("7.5 % 2", "1.5"),
+            ("7 ~/ 2", "3"),
+            ("-7 ~/ 2", "-4"),
+            ("7.0 ~/ 2", "3.0"),
+            ("7 / 2", "3.5"),
+            ("2 ** 10", "1024"),
+            ("2 ** 3 ** 2", "512"),
+            ("-2 ** 2", "-4"),
+            ("(-2) ** 2", "4"),
+            ("2 ** -1", "0.5"),
+            ("2.0 ** 2", "4.0"),
+            ("2 * 3 ** 2", "18"),
+            ("1 + 10 % 4 * 2", "5"),
+            ("1 / 0", "division by zero"),
+            ("1 ~/ 0", "division by zero"),
+            ("1 % 0", "modulo by zero"),
+            ("let x = 1 x /= 0", "division by zero"),
         ];
 
         test_eval_string(&test_case)
@@ -1,52 +1,365 @@
-use std::fmt::Display;
+use std::{cmp::Ordering, collections::BTreeMap, fmt::Display};
 
-use crate::ast::{BlockStatment, Expression};
+use crate::{
+    ast::{BlockStatment, Op, Parameter},
+    token::Span,
+};
 
 use super::environment::Env;
 
-pub type EvalError = String;
-pub type BuiltinFunc = fn(Vec<Object>) -> Object;
+#[derive(PartialEq, Debug, Clone)]
+pub enum EvalErrorKind {
+    /// The operands of an infix expression are of different types
+    TypeMismatch {
+        left: String,
+        op: Op,
+        right: String,
+    },
+    /// The operator is not defined for its operands, `left` is None for prefix operators
+    UnknownOperator {
+        left: Option<String>,
+        op: Op,
+        right: String,
+    },
+    UndefinedVariable {
+        name: String,
+    },
+    /// The result of integer arithmetic does not fit in an integer, `left` is None for prefix
+    /// operators
+    IntegerOverflow {
+        left: Option<i64>,
+        op: Op,
+        right: i64,
+    },
+    /// `/` or `~/` with zero on the right
+    DivisionByZero,
+    /// `%` with zero on the right
+    ModuloByZero,
+    /// Assignment to a name that was never declared with `let`
+    UndeclaredAssignment {
+        name: String,
+    },
+    /// Assignment to, or redeclaration of, a name declared with `const`
+    ConstAssignment {
+        name: String,
+    },
+    ArityMismatch {
+        got: usize,
+        want: usize,
+    },
+    /// A call to a function with default or rest parameters has too few or too many arguments,
+    /// `max` is None when there is a rest parameter
+    ArityOutOfRange {
+        got: usize,
+        min: usize,
+        max: Option<usize>,
+    },
+    NotCallable {
+        found: String,
+    },
+    /// A named argument that does not match any parameter of the function called
+    UnknownArgument {
+        function: String,
+        name: String,
+    },
+    /// A parameter that is given a value by more than one argument
+    DuplicateArgument {
+        function: String,
+        name: String,
+    },
+    /// A parameter without a default that no argument gave a value to
+    MissingArgument {
+        function: String,
+        name: String,
+    },
+    /// An argument to a builtin function has a type it does not support
+    UnsupportedArgument {
+        function: String,
+        got: String,
+    },
+    /// The index is outside of the collection, `index` is the one the user wrote
+    IndexOutOfBounds {
+        index: i64,
+        length: usize,
+    },
+    /// A float that is used as an index
+    InvalidIndex {
+        index: String,
+    },
+    /// The value can not be indexed, or not with an index of that type
+    IndexNotSupported {
+        left: String,
+        index: String,
+    },
+    /// A value that a `for` loop can not iterate over
+    NotIterable {
+        found: String,
+    },
+    /// A value of a type that can not be used as a hash key
+    UnhashableKey {
+        found: String,
+    },
+    /// An argument to a builtin function has the right type but a value it can not handle
+    InvalidArgument {
+        function: String,
+        message: String,
+    },
+    /// More calls are nested than the evaluator allows, `limit` is the maximum call depth
+    StackOverflow {
+        limit: usize,
+    },
+    /// A string would be longer than the evaluator allows, `max` is the limit in bytes
+    StringTooLong {
+        max: usize,
+    },
+    /// The native stack ran out before the call depth limit was reached, `depth` is the number of
+    /// nested calls at that point
+    StackExhausted {
+        depth: usize,
+    },
+}
+
+impl Display for EvalErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalErrorKind::TypeMismatch { left, op, right } => {
+                write!(f, "type mismatch: {} {} {}", left, op, right)
+            }
+            EvalErrorKind::UnknownOperator {
+                left: Some(left),
+                op,
+                right,
+            } => write!(f, "unknown operator: {} {} {}", left, op, right),
+            EvalErrorKind::UnknownOperator {
+                left: None,
+                op,
+                right,
+            } => write!(f, "unknown operator: {}{}", op, right),
+            EvalErrorKind::UndefinedVariable { name } => {
+                write!(f, "identifier not found: {}", name)
+            }
+            EvalErrorKind::IntegerOverflow {
+                left: Some(left),
+                op,
+                right,
+            } => write!(f, "integer overflow: {} {} {}", left, op, right),
+            EvalErrorKind::IntegerOverflow {
+                left: None,
+                op,
+                right,
+            } => write!(f, "integer overflow: {}{}", op, right),
+            EvalErrorKind::DivisionByZero => write!(f, "division by zero"),
+            EvalErrorKind::ModuloByZero => write!(f, "modulo by zero"),
+            EvalErrorKind::UndeclaredAssignment { name } => {
+                write!(f, "cannot assign to undeclared variable: {}", name)
+            }
+            EvalErrorKind::ConstAssignment { name } => {
+                write!(f, "cannot assign to constant: {}", name)
+            }
+            EvalErrorKind::ArityMismatch { got, want } => {
+                write!(f, "wrong number of arguments. got={}, want={}", got, want)
+            }
+            EvalErrorKind::ArityOutOfRange {
+                got,
+                min,
+                max: Some(max),
+            } => write!(
+                f,
+                "wrong number of arguments. got={}, want={}..{}",
+                got, min, max
+            ),
+            EvalErrorKind::ArityOutOfRange {
+                got,
+                min,
+                max: None,
+            } => write!(
+                f,
+                "wrong number of arguments. got={}, want={} or more",
+                got, min
+            ),
+            EvalErrorKind::NotCallable { found } => write!(f, "not a function: {}", found),
+            EvalErrorKind::UnknownArgument { function, name } => {
+                write!(f, "unknown argument `{}` in call to `{}`", name, function)
+            }
+            EvalErrorKind::DuplicateArgument { function, name } => write!(
+                f,
+                "argument `{}` is given more than once in call to `{}`",
+                name, function
+            ),
+            EvalErrorKind::MissingArgument { function, name } => {
+                write!(f, "missing argument `{}` in call to `{}`", name, function)
+            }
+            EvalErrorKind::UnsupportedArgument { function, got } => {
+                write!(f, "argument to '{}' not supported, got {}", function, got)
+            }
+            EvalErrorKind::IndexOutOfBounds { index, length } => {
+                write!(f, "index out of bounds: index {}, length {}", index, length)
+            }
+            EvalErrorKind::InvalidIndex { index } => {
+                write!(f, "index must be an integer, got {}", index)
+            }
+            EvalErrorKind::IndexNotSupported { left, index } => {
+                write!(f, "index operator not supported: {}[{}]", left, index)
+            }
+            EvalErrorKind::NotIterable { found } => write!(f, "not iterable: {}", found),
+            EvalErrorKind::UnhashableKey { found } => {
+                write!(f, "unusable as hash key: {}", found)
+            }
+            EvalErrorKind::InvalidArgument { function, message } => {
+                write!(f, "invalid argument to '{}': {}", function, message)
+            }
+            EvalErrorKind::StackOverflow { limit } => {
+                write!(f, "stack overflow: more than {} nested calls", limit)
+            }
+            EvalErrorKind::StringTooLong { max } => {
+                write!(f, "string too long: more than {} bytes", max)
+            }
+            EvalErrorKind::StackExhausted { depth } => {
+                write!(
+                    f,
+                    "stack overflow: out of stack after {} nested calls",
+                    depth
+                )
+            }
+        }
+    }
+}
+
+/// A function call that was being evaluated when an error occurred
+#[derive(PartialEq, Debug, Clone)]
+pub struct StackFrame {
+    pub function: String,
+    /// Where the function was called from
+    pub span: Span,
+}
+
+/// A runtime error, with the span of the expression that caused it and the calls that led to it,
+/// innermost call first
+#[derive(PartialEq, Debug, Clone)]
+pub struct EvalError {
+    pub kind: EvalErrorKind,
+    pub span: Option<Span>,
+    pub trace: Vec<StackFrame>,
+}
+
+impl EvalError {
+    pub fn new(kind: EvalErrorKind) -> Self {
+        Self {
+            kind,
+            span: None,
+            trace: Vec::new(),
+        }
+    }
+
+    /// Attaches a span to the error, unless a more precise one was already attached
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+
+    /// Records that the error happened inside a call to `function`
+    pub fn with_frame(mut self, function: String, span: Span) -> Self {
+        self.trace.push(StackFrame { function, span });
+        self
+    }
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+pub type BuiltinFunc = fn(Vec<Object>) -> Result<Object, EvalError>;
+
+/// A function implemented in Rust, two builtins are the same when they have the same name
+#[derive(Debug, Clone)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub func: BuiltinFunc,
+}
+
+impl PartialEq for Builtin {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
 
 #[derive(PartialEq, Debug, Clone)]
 #[allow(non_snake_case, dead_code)]
 pub enum Object {
-    Integer(f64),
+    Int(i64),
+    Float(f64),
     String(String),
     Boolean(bool),
     Nil,
+    Array(Vec<Object>),
+    Hash(BTreeMap<HashKey, Object>),
     Return(Box<Object>),
+    /// Signals that a `break` was evaluated, like `Return` it stops the enclosing blocks until
+    /// the loop is reached
+    Break,
+    Continue,
+    /// `name` is set for functions that were declared with `fn name() {}`
     Function {
-        Parameters: Option<Vec<Expression>>,
+        Parameters: Option<Vec<Parameter>>,
         Body: BlockStatment,
         env: Env,
+        name: Option<String>,
     },
-    Builtin(BuiltinFunc),
+    Builtin(Builtin),
     Error(String),
 }
 
 impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Object::Integer(i) => write!(f, "{}", i),
+            Object::Int(i) => write!(f, "{}", i),
+            // Debug keeps the `.0` of whole floats so they can be told apart from integers
+            Object::Float(n) => write!(f, "{:?}", n),
             Object::String(s) => write!(f, "{}", s),
             Object::Boolean(b) => write!(f, "{}", b),
             Object::Nil => write!(f, "null"),
+            Object::Array(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|e| e.inspect())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "[{}]", elements)
+            }
+            Object::Hash(pairs) => {
+                let pairs = pairs
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("{}: {}", key.to_object().inspect(), value.inspect())
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{{{}}}", pairs)
+            }
             Object::Return(v) => write!(f, "{}", v),
+            Object::Break => write!(f, "break"),
+            Object::Continue => write!(f, "continue"),
             Object::Error(e) => write!(f, "{}", e),
             Object::Function {
                 Parameters,
                 Body,
                 env: _,
+                name,
             } => {
                 let params = match Parameters {
                     Some(v) => v
                         .iter()
-                        .map(|exp| exp.to_string())
+                        .map(|param| param.to_string())
                         .collect::<Vec<String>>()
                         .join(", "),
                     None => String::from(""),
                 };
-                write!(f, "fn({}) {{ {} }}", params, Body)
+                match name {
+                    Some(name) => write!(f, "fn {}({}) {{ {} }}", name, params, Body),
+                    None => write!(f, "fn({}) {{ {} }}", params, Body),
+                }
             }
             Object::Builtin(_) => write!(f, "[BUILTIN FUNCTION]"),
         }
@@ -54,20 +367,116 @@ impl Display for Object {
 }
 
 impl Object {
+    /// Like `to_string` but quotes strings, used to show values nested inside other values
+    pub fn inspect(&self) -> String {
+        match self {
+            Object::String(s) => format!("{:?}", s),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Returns the value of an integer or float as a float, None for other objects
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Object::Int(n) => Some(*n as f64),
+            Object::Float(n) => Some(*n),
+            _ => None,
+        }
+    }
+
     pub fn type_info(&self) -> String {
         match self {
-            Object::Integer(_) => format!("INT"),
-            Object::String(_) => format!("STRING"),
-            Object::Boolean(_) => format!("BOOLEAN"),
-            Object::Nil => format!("Nil"),
+            Object::Int(_) => "INT".to_string(),
+            Object::Float(_) => "FLOAT".to_string(),
+            Object::String(_) => "STRING".to_string(),
+            Object::Boolean(_) => "BOOLEAN".to_string(),
+            Object::Nil => "Nil".to_string(),
+            Object::Array(_) => "ARRAY".to_string(),
+            Object::Hash(_) => "HASH".to_string(),
             Object::Return(e) => e.type_info(),
-            Object::Error(_) => format!("ERROR"),
-            Object::Function {
-                Parameters: _,
-                Body: _,
-                env: _,
-            } => format!("FUNCTION"),
-            Object::Builtin(_) => format!("FUNCTION"),
+            Object::Break => "BREAK".to_string(),
+            Object::Continue => "CONTINUE".to_string(),
+            Object::Error(_) => "ERROR".to_string(),
+            Object::Function { .. } => "FUNCTION".to_string(),
+            Object::Builtin(_) => "FUNCTION".to_string(),
         }
     }
 }
+
+/// The objects that can be used as keys of a hash. Floats without a fraction are stored as
+/// integers, so `1` and `1.0` are the same key, and the other floats are compared with
+/// `total_cmp` so they can be ordered
+#[derive(Debug, Clone)]
+pub enum HashKey {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Boolean(bool),
+}
+
+impl HashKey {
+    pub fn from_object(obj: &Object) -> Result<Self, EvalError> {
+        match obj {
+            Object::Int(n) => Ok(HashKey::Int(*n)),
+            Object::Float(n)
+                if n.fract() == 0.0 && (i64::MIN as f64..i64::MAX as f64).contains(n) =>
+            {
+                Ok(HashKey::Int(*n as i64))
+            }
+            Object::Float(n) => Ok(HashKey::Float(*n)),
+            Object::String(s) => Ok(HashKey::String(s.clone())),
+            Object::Boolean(b) => Ok(HashKey::Boolean(*b)),
+            _ => Err(EvalError::new(EvalErrorKind::UnhashableKey {
+                found: obj.type_info(),
+            })),
+        }
+    }
+
+    pub fn to_object(&self) -> Object {
+        match self {
+            HashKey::Int(n) => Object::Int(*n),
+            HashKey::Float(n) => Object::Float(*n),
+            HashKey::String(s) => Object::String(s.clone()),
+            HashKey::Boolean(b) => Object::Boolean(*b),
+        }
+    }
+
+    /// Orders keys of different types: booleans, then numbers, then strings
+    fn rank(&self) -> u8 {
+        match self {
+            HashKey::Boolean(_) => 0,
+            HashKey::Int(_) | HashKey::Float(_) => 1,
+            HashKey::String(_) => 2,
+        }
+    }
+}
+
+impl Ord for HashKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (HashKey::Int(a), HashKey::Int(b)) => a.cmp(b),
+            (HashKey::Float(a), HashKey::Float(b)) => a.total_cmp(b),
+            (HashKey::Int(a), HashKey::Float(b)) => (*a as f64).total_cmp(b).then(Ordering::Less),
+            (HashKey::Float(a), HashKey::Int(b)) => {
+                a.total_cmp(&(*b as f64)).then(Ordering::Greater)
+            }
+            (HashKey::String(a), HashKey::String(b)) => a.cmp(b),
+            (HashKey::Boolean(a), HashKey::Boolean(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for HashKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HashKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HashKey {}
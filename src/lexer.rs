@@ -1,29 +1,120 @@
-use crate::token::{Token, TokenType};
+use crate::token::{Span, Token, TokenType};
+use std::fmt::Display;
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum LexError {
+    /// A character that does not start any token
+    UnexpectedCharacter { found: char, span: Span },
+    /// A string that is still open at the end of the source, `span` starts at its opening quote
+    UnterminatedString { span: Span },
+    /// A backslash in a string followed by a character that has no escape
+    InvalidEscape { found: char, span: Span },
+    /// `\u` not followed by the hexadecimal code of a character in braces
+    InvalidUnicodeEscape { span: Span },
+    /// A `/*` without a matching `*/`, `span` starts at the outermost `/*`
+    UnterminatedComment { span: Span },
+}
+
+impl LexError {
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::UnexpectedCharacter { span, .. }
+            | LexError::UnterminatedString { span }
+            | LexError::InvalidEscape { span, .. }
+            | LexError::InvalidUnicodeEscape { span }
+            | LexError::UnterminatedComment { span } => *span,
+        }
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexError::UnexpectedCharacter { found, .. } => {
+                write!(f, "unexpected character: `{}`", found.escape_default())
+            }
+            LexError::UnterminatedString { .. } => write!(f, "unterminated string"),
+            LexError::InvalidEscape { found, .. } => {
+                write!(f, "unknown escape sequence: `\\{}`", found.escape_default())
+            }
+            LexError::InvalidUnicodeEscape { .. } => {
+                write!(
+                    f,
+                    "invalid unicode escape, expected `\\u{{...}}` with a hexadecimal code"
+                )
+            }
+            LexError::UnterminatedComment { .. } => {
+                write!(f, "unterminated block comment: `/*` is never closed")
+            }
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Lexer {
-    pub tokens: Vec<Token>,
     source: Vec<char>,
     pub current: usize,
     next: usize,
     char: char,
+    // Position of `char` in the source
+    offset: usize,
+    line: usize,
+    column: usize,
+    /// For every `${` of a string that is being lexed: the quote of the string and how many
+    /// braces are open inside the interpolation
+    interpolations: Vec<(char, usize)>,
+    /// Whether comments are returned as `Comment` tokens instead of being skipped
+    keep_comments: bool,
 }
 
 impl Lexer {
     pub fn new(source: String) -> Self {
         let mut s = Self {
-            tokens: Vec::new(),
             source: source.chars().collect(),
             current: 0,
             next: 1,
             char: '\0',
+            offset: 0,
+            line: 1,
+            column: 1,
+            interpolations: Vec::new(),
+            keep_comments: false,
         };
 
-        s.char = s.source[s.current];
+        s.char = s.source.first().copied().unwrap_or('\0');
         s
     }
 
+    /// Creates a lexer for source code that starts at `start`, so its spans point into a larger
+    /// body of code like the one a `SourceMap` keeps
+    pub fn starting_at(source: String, start: Span) -> Self {
+        Self {
+            offset: start.start,
+            line: start.line,
+            column: start.column,
+            ..Self::new(source)
+        }
+    }
+
+    /// Creates a lexer that returns comments as tokens, so tools like a formatter can keep them
+    pub fn with_comments(source: String) -> Self {
+        Self {
+            keep_comments: true,
+            ..Self::new(source)
+        }
+    }
+
     pub fn read(&mut self) {
+        if self.current < self.source.len() {
+            self.offset += self.char.len_utf8();
+            if self.char == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+
         if self.next >= self.source.len() {
             self.char = '\0'
         } else {
@@ -34,33 +125,122 @@ impl Lexer {
         self.next = self.current + 1;
     }
 
+    /// Returns the character after the current one without reading it
+    fn peek_char(&self) -> char {
+        self.source.get(self.next).copied().unwrap_or('\0')
+    }
+
+    /// Reads the next character if it is `expected`, returning the token type for the one or two
+    /// character operator that was read
+    fn match_operator(
+        &mut self,
+        expected: char,
+        double: TokenType,
+        single: TokenType,
+    ) -> TokenType {
+        if self.peek_char() == expected {
+            self.read();
+            double
+        } else {
+            single
+        }
+    }
+
+    /// Reads past an operator made of the characters in `literal`
+    fn read_operator(&mut self, literal: &str) {
+        for _ in literal.chars() {
+            self.read();
+        }
+    }
+
     pub fn skip_whitespace(&mut self) {
         while self.char.is_whitespace() {
             self.read()
         }
     }
 
-    pub fn match_token(&mut self) -> Token {
+    /// Scans a number literal: decimal digits with an optional fraction and exponent, or the
+    /// digits of a hexadecimal or binary integer after `0x` or `0b`. `_` separators are left out
+    /// of the literal
+    fn scan_number(&mut self) -> Token {
+        let mut buffer = String::new();
+
+        if self.char == '0' && matches!(self.peek_char(), 'x' | 'b') {
+            buffer.push(self.char);
+            self.read();
+            buffer.push(self.char);
+            self.read();
+
+            // The parser rejects letters that are not digits in the base of the literal
+            self.read_digits(&mut buffer, |c| c.is_ascii_alphanumeric());
+            return Token::new(TokenType::Number, buffer);
+        }
+
+        self.read_digits(&mut buffer, |c| c.is_ascii_digit());
+
+        // A `.` only belongs to the number when a digit follows it
+        if self.char == '.' && self.peek_char().is_ascii_digit() {
+            buffer.push(self.char);
+            self.read();
+            self.read_digits(&mut buffer, |c| c.is_ascii_digit());
+        }
+
+        let signed = matches!(self.peek_char(), '+' | '-');
+        let exponent_follows = self
+            .source
+            .get(self.next + usize::from(signed))
+            .is_some_and(|c| c.is_ascii_digit());
+        if matches!(self.char, 'e' | 'E') && exponent_follows {
+            buffer.push(self.char);
+            self.read();
+            if signed {
+                buffer.push(self.char);
+                self.read();
+            }
+            self.read_digits(&mut buffer, |c| c.is_ascii_digit());
+        }
+
+        Token::new(TokenType::Number, buffer)
+    }
+
+    fn read_digits(&mut self, buffer: &mut String, is_digit: impl Fn(char) -> bool) {
+        while is_digit(self.char) || self.char == '_' {
+            if self.char != '_' {
+                buffer.push(self.char);
+            }
+            self.read();
+        }
+    }
+
+    /// Returns an empty span at the current position of the lexer
+    pub fn location(&self) -> Span {
+        Span::new(self.offset, self.offset, self.line, self.column)
+    }
+
+    /// Reads the next token, recording where in the source it was found
+    pub fn match_token(&mut self) -> Result<Token, LexError> {
         self.skip_whitespace();
 
-        match self.char {
+        let start = self.location();
+        let mut token = self.scan_token(start)?;
+        token.span = start.to(self.location());
+
+        Ok(token)
+    }
+
+    /// Scans the token starting at the current character, `start` is its position
+    fn scan_token(&mut self, start: Span) -> Result<Token, LexError> {
+        let token = match self.char {
             // Single Character Tokens
             '=' => {
-                let mut buffer: String = String::new();
-                buffer.push(self.char);
+                let token_type = self.match_operator('=', TokenType::EqualEqual, TokenType::Assign);
                 self.read();
-
-                while !self.char.is_whitespace() {
-                    buffer.push(self.char);
-                    self.read();
-                }
-                let token_type: TokenType = match buffer.as_str() {
-                    "=" => TokenType::Assign,
-                    "==" => TokenType::EqualEqual,
-                    _ => unimplemented!(),
+                let literal = match token_type {
+                    TokenType::EqualEqual => "==",
+                    _ => "=",
                 };
 
-                Token::new(token_type, buffer)
+                Token::new(token_type, literal.to_owned())
             }
             ',' => {
                 self.read();
@@ -70,67 +250,108 @@ impl Lexer {
                 self.read();
                 Token::new(TokenType::SemiColon, ";".to_owned())
             }
+            ':' => {
+                self.read();
+                Token::new(TokenType::Colon, ":".to_owned())
+            }
             '<' => {
-                let mut buffer: String = String::new();
-                buffer.push(self.char);
+                let token_type = self.match_operator('=', TokenType::LessEqual, TokenType::Less);
                 self.read();
-
-                while !self.char.is_whitespace() {
-                    buffer.push(self.char);
-                    self.read();
-                }
-                let token_type: TokenType = match buffer.as_str() {
-                    "<" => TokenType::Less,
-                    "<=" => TokenType::LessEqual,
-                    _ => unimplemented!(),
+                let literal = match token_type {
+                    TokenType::LessEqual => "<=",
+                    _ => "<",
                 };
 
-                Token::new(token_type, buffer)
+                Token::new(token_type, literal.to_owned())
             }
             '>' => {
-                let mut buffer: String = String::new();
-                buffer.push(self.char);
+                let token_type =
+                    self.match_operator('=', TokenType::GreaterEqual, TokenType::Greater);
                 self.read();
-
-                while !self.char.is_whitespace() {
-                    buffer.push(self.char);
-                    self.read();
-                }
-                let token_type: TokenType = match buffer.as_str() {
-                    ">" => TokenType::Greater,
-                    ">=" => TokenType::GreaterEqual,
-                    _ => unimplemented!(),
+                let literal = match token_type {
+                    TokenType::GreaterEqual => ">=",
+                    _ => ">",
                 };
 
-                Token::new(token_type, buffer)
+                Token::new(token_type, literal.to_owned())
             }
             '+' => {
+                let token_type =
+                    self.match_operator('=', TokenType::PlusEqual, TokenType::Addition);
                 self.read();
-                Token::new(TokenType::Addition, "+".to_owned())
+                let literal = match token_type {
+                    TokenType::PlusEqual => "+=",
+                    _ => "+",
+                };
+
+                Token::new(token_type, literal.to_owned())
             }
             '-' => {
+                let token_type = self.match_operator('=', TokenType::MinusEqual, TokenType::Minus);
                 self.read();
-                Token::new(TokenType::Minus, "-".to_owned())
+                let literal = match token_type {
+                    TokenType::MinusEqual => "-=",
+                    _ => "-",
+                };
+
+                Token::new(token_type, literal.to_owned())
             }
             '*' => {
-                self.read();
-                Token::new(TokenType::Star, "*".to_owned())
+                let (token_type, literal) = match self.peek_char() {
+                    '*' => (TokenType::StarStar, "**"),
+                    '=' => (TokenType::StarEqual, "*="),
+                    _ => (TokenType::Star, "*"),
+                };
+                self.read_operator(literal);
+
+                Token::new(token_type, literal.to_owned())
+            }
+            '/' if self.peek_char() == '/' => {
+                let mut buffer = String::new();
+                while self.char != '\n' && self.current < self.source.len() {
+                    buffer.push(self.char);
+                    self.read();
+                }
+                Token::new(TokenType::Comment, buffer)
+            }
+            '/' if self.peek_char() == '*' => return self.scan_block_comment(start),
+            // Floor division, spelt `~/` because `//` starts a line comment
+            '~' if self.peek_char() == '/' => {
+                self.read_operator("~/");
+                Token::new(TokenType::TildeSlash, "~/".to_owned())
             }
             '/' => {
+                let (token_type, literal) = match self.peek_char() {
+                    '=' => (TokenType::SlashEqual, "/="),
+                    _ => (TokenType::Division, "/"),
+                };
+                self.read_operator(literal);
+
+                Token::new(token_type, literal.to_owned())
+            }
+            '%' => {
+                self.read();
+                Token::new(TokenType::Percent, "%".to_owned())
+            }
+            '&' if self.peek_char() == '&' => {
+                self.read();
+                self.read();
+                Token::new(TokenType::And, "&&".to_owned())
+            }
+            '|' if self.peek_char() == '|' => {
+                self.read();
                 self.read();
-                Token::new(TokenType::Division, "/".to_owned())
+                Token::new(TokenType::Or, "||".to_owned())
             }
             '!' => {
-                let mut buffer: String = String::new();
-                buffer.push(self.char);
+                let token_type = self.match_operator('=', TokenType::BangEqual, TokenType::Bang);
                 self.read();
+                let literal = match token_type {
+                    TokenType::BangEqual => "!=",
+                    _ => "!",
+                };
 
-                if self.source[self.current] == '=' {
-                    self.read();
-                    Token::new(TokenType::BangEqual, "!=".to_owned())
-                } else {
-                    Token::new(TokenType::Bang, "!".to_owned())
-                }
+                Token::new(token_type, literal.to_owned())
             }
             '(' => {
                 self.read();
@@ -141,59 +362,55 @@ impl Lexer {
                 Token::new(TokenType::RightParen, ")".to_owned())
             }
             '{' => {
+                if let Some((_, depth)) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
                 self.read();
                 Token::new(TokenType::LeftBrace, "{".to_owned())
             }
             '}' => {
+                match self.interpolations.last_mut() {
+                    // The end of an interpolation, the string continues after it
+                    Some((quote, 0)) => {
+                        let quote = *quote;
+                        self.interpolations.pop();
+                        self.read();
+                        return self.scan_string(quote, start, true);
+                    }
+                    Some((_, depth)) => *depth -= 1,
+                    None => {}
+                }
                 self.read();
                 Token::new(TokenType::RightBrace, "}".to_owned())
             }
-            // String Token
-            '\"' | '\'' => {
-                let qoute_type = self.char;
+            '.' if self.peek_char() == '.' && self.source.get(self.next + 1) == Some(&'.') => {
+                self.read_operator("...");
+                Token::new(TokenType::Ellipsis, "...".to_owned())
+            }
+            '[' => {
                 self.read();
-                let mut buffer = String::new();
-
-                while self.char != qoute_type {
-                    buffer.push(self.char);
-                    self.read();
-                }
+                Token::new(TokenType::LeftBracket, "[".to_owned())
+            }
+            ']' => {
                 self.read();
-                Token::new(TokenType::String, buffer)
+                Token::new(TokenType::RightBracket, "]".to_owned())
             }
-            _ if self.char.is_numeric() => {
-                let mut buffer: String = String::new();
-                buffer.push(self.char);
+            // String Token
+            '\"' | '\'' => {
+                let quote = self.char;
                 self.read();
-
-                loop {
-                    if self.current >= self.source.len() {
-                        break;
-                    }
-
-                    if self.char == '_' {
-                        self.read()
-                    }
-
-                    if !self.char.is_numeric() && self.char != '.' {
-                        break;
-                    }
-
-                    buffer.push(self.char);
-                    self.read()
-                }
-
-                Token::new(TokenType::Number, buffer)
+                return self.scan_string(quote, start, false);
             }
+            _ if self.char.is_ascii_digit() => self.scan_number(),
             // Let and Variable names
-            _ if self.char.is_alphabetic() => {
+            _ if self.char.is_alphabetic() || self.char == '_' => {
                 let mut buffer = String::new();
 
                 buffer.push(self.char);
 
                 self.read();
 
-                while self.char.is_alphabetic() {
+                while self.char.is_alphanumeric() || self.char == '_' {
                     buffer.push(self.char);
 
                     self.read();
@@ -201,6 +418,9 @@ impl Lexer {
 
                 let token_type: TokenType = match buffer.as_str() {
                     "let" => TokenType::Let,
+                    "const" => TokenType::Const,
+                    "and" => TokenType::And,
+                    "or" => TokenType::Or,
                     "if" => TokenType::If,
                     "True" => TokenType::Bool,
                     "False" => TokenType::Bool,
@@ -208,30 +428,176 @@ impl Lexer {
                     "fn" => TokenType::Fn,
                     "else" => TokenType::Else,
                     "return" => TokenType::Return,
+                    "while" => TokenType::While,
+                    "for" => TokenType::For,
+                    "in" => TokenType::In,
+                    "break" => TokenType::Break,
+                    "continue" => TokenType::Continue,
                     _ => TokenType::Identifier,
                 };
 
                 Token::new(token_type, buffer)
             }
             _ => {
-                dbg!("{}", self.char);
-                unimplemented!()
+                let found = self.char;
+                self.read();
+                return Err(LexError::UnexpectedCharacter {
+                    found,
+                    span: start.to(self.location()),
+                });
+            }
+        };
+
+        Ok(token)
+    }
+
+    /// Scans a `/* */` comment including its delimiters, comments nest so every `/*` inside of it
+    /// needs its own `*/`
+    fn scan_block_comment(&mut self, start: Span) -> Result<Token, LexError> {
+        let mut buffer = String::new();
+        let mut depth = 0;
+
+        loop {
+            if self.current >= self.source.len() {
+                return Err(LexError::UnterminatedComment {
+                    span: start.to(self.location()),
+                });
+            }
+
+            let delimiter = match (self.char, self.peek_char()) {
+                ('/', '*') => "/*",
+                ('*', '/') => "*/",
+                (c, _) => {
+                    buffer.push(c);
+                    self.read();
+                    continue;
+                }
+            };
+            buffer.push_str(delimiter);
+            self.read_operator(delimiter);
+
+            depth += if delimiter == "/*" { 1 } else { -1 };
+            if depth == 0 {
+                break;
             }
         }
+
+        Ok(Token::new(TokenType::Comment, buffer))
     }
-}
 
-impl Iterator for Lexer {
-    type Item = Token;
+    /// Scans the text of a string up to its closing quote or up to the next `${`. `resumed` is
+    /// set when the text follows the `}` of an interpolation. An invalid escape is only reported
+    /// once the text has been read, so the rest of the string is not lexed as code
+    fn scan_string(&mut self, quote: char, start: Span, resumed: bool) -> Result<Token, LexError> {
+        let mut buffer = String::new();
+        let mut error = None;
 
-    fn next(&mut self) -> Option<Token> {
-        if self.next >= self.source.len() {
-            return None;
+        let token_type = loop {
+            match self.char {
+                c if c == quote => {
+                    self.read();
+                    break if resumed {
+                        TokenType::TemplateEnd
+                    } else {
+                        TokenType::String
+                    };
+                }
+                '$' if self.peek_char() == '{' => {
+                    self.read();
+                    self.read();
+                    self.interpolations.push((quote, 0));
+                    break if resumed {
+                        TokenType::TemplateMiddle
+                    } else {
+                        TokenType::TemplateStart
+                    };
+                }
+                '\0' if self.current >= self.source.len() => {
+                    return Err(LexError::UnterminatedString {
+                        span: start.to(self.location()),
+                    });
+                }
+                '\\' => {
+                    let escape_start = self.location();
+                    self.read();
+                    match self.scan_escape(escape_start) {
+                        Ok(c) => buffer.push(c),
+                        Err(e) => {
+                            error.get_or_insert(e);
+                        }
+                    }
+                }
+                c => {
+                    buffer.push(c);
+                    self.read();
+                }
+            }
+        };
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(Token::new(token_type, buffer)),
         }
+    }
 
-        let token = self.match_token();
+    /// Reads the escape sequence after a backslash and returns the character it stands for
+    fn scan_escape(&mut self, start: Span) -> Result<char, LexError> {
+        let escaped = self.char;
+        self.read();
+
+        match escaped {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' | '"' | '\'' | '$' => Ok(escaped),
+            'u' => {
+                let mut digits = String::new();
+                let braced = self.char == '{';
+                if braced {
+                    self.read();
+                    while self.char.is_ascii_hexdigit() {
+                        digits.push(self.char);
+                        self.read();
+                    }
+                }
 
-        Some(token)
+                let closed = braced && self.char == '}';
+                if closed {
+                    self.read();
+                }
+
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .filter(|_| closed)
+                    .ok_or(LexError::InvalidUnicodeEscape {
+                        span: start.to(self.location()),
+                    })
+            }
+            _ => Err(LexError::InvalidEscape {
+                found: escaped,
+                span: start.to(self.location()),
+            }),
+        }
+    }
+}
+
+impl Iterator for Lexer {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Result<Token, LexError>> {
+        loop {
+            self.skip_whitespace();
+            if self.current >= self.source.len() {
+                return None;
+            }
+
+            match self.match_token() {
+                Ok(token) if token.token_type == TokenType::Comment && !self.keep_comments => {}
+                token => return Some(token),
+            }
+        }
     }
 }
 
@@ -239,10 +605,22 @@ impl Iterator for Lexer {
 mod tests {
     use super::*;
 
+    /// Lexes `source` with the spans of the tokens left out, so tests can compare what was
+    /// lexed. Errors keep their spans
+    fn lex(source: &str) -> Vec<Result<Token, LexError>> {
+        Lexer::new(source.to_string())
+            .map(|token| {
+                token.map(|token| Token {
+                    span: Span::default(),
+                    ..token
+                })
+            })
+            .collect()
+    }
+
     #[test]
     fn lexer_test() {
-        let lexer = Lexer::new(String::from(
-            "let x = 123
+        let tokens = lex("let x = 123
         let y = \"hello world\"
         let number = 420 + 69 - 1
         nil True False ! != ( ) == >= <= * ,
@@ -251,14 +629,13 @@ mod tests {
             return True
         } else {
             return False
-        }",
-        ));
+        }");
 
         let mut array_of_tokens: Vec<Token> = Vec::new();
 
-        for t in lexer {
+        for t in tokens {
             println!("{:?}", t);
-            array_of_tokens.push(t);
+            array_of_tokens.push(t.unwrap());
         }
 
         assert_eq!(array_of_tokens.len(), 49);
@@ -359,4 +736,242 @@ mod tests {
             Token::new(TokenType::Comma, ",".to_string())
         );
     }
+
+    #[test]
+    fn lexer_spans() {
+        let lexer = Lexer::new(String::from("let x = 12\n  \"hé\" + y"));
+
+        let spans: Vec<(usize, usize, usize, usize)> = lexer
+            .map(|t| t.unwrap().span)
+            .map(|s| (s.start, s.end, s.line, s.column))
+            .collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                (0, 3, 1, 1),
+                (4, 5, 1, 5),
+                (6, 7, 1, 7),
+                (8, 10, 1, 9),
+                (13, 18, 2, 3),
+                (19, 20, 2, 8),
+                (21, 22, 2, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_spans_starting_at() {
+        let lexer = Lexer::starting_at(String::from("a\n b"), Span::new(30, 30, 4, 1));
+
+        let spans: Vec<(usize, usize, usize, usize)> = lexer
+            .map(|t| t.unwrap().span)
+            .map(|s| (s.start, s.end, s.line, s.column))
+            .collect();
+
+        assert_eq!(spans, vec![(30, 31, 4, 1), (33, 34, 5, 2)]);
+    }
+
+    #[test]
+    fn lexer_numbers() {
+        let lexer = Lexer::new(String::from("1_000 0xFF_FF 0b1010 1.5 1e9 2.5E-3 3e"));
+
+        let tokens: Vec<(TokenType, String)> = lexer
+            .map(|t| t.unwrap())
+            .map(|t| (t.token_type, t.literal))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenType::Number, "1000".to_string()),
+                (TokenType::Number, "0xFFFF".to_string()),
+                (TokenType::Number, "0b1010".to_string()),
+                (TokenType::Number, "1.5".to_string()),
+                (TokenType::Number, "1e9".to_string()),
+                (TokenType::Number, "2.5E-3".to_string()),
+                (TokenType::Number, "3".to_string()),
+                (TokenType::Identifier, "e".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_compound_operators() {
+        let lexer = Lexer::new(String::from("+= -= *= /= + - && || and or ** ~/ % * / ..."));
+
+        let tokens: Vec<TokenType> = lexer.map(|t| t.unwrap().token_type).collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::PlusEqual,
+                TokenType::MinusEqual,
+                TokenType::StarEqual,
+                TokenType::SlashEqual,
+                TokenType::Addition,
+                TokenType::Minus,
+                TokenType::And,
+                TokenType::Or,
+                TokenType::And,
+                TokenType::Or,
+                TokenType::StarStar,
+                TokenType::TildeSlash,
+                TokenType::Percent,
+                TokenType::Star,
+                TokenType::Division,
+                TokenType::Ellipsis,
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_errors() {
+        let tokens = lex("let x = 5 $ 2\n==x=>y .5");
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::new(TokenType::Let, "let".to_string())),
+                Ok(Token::new(TokenType::Identifier, "x".to_string())),
+                Ok(Token::new(TokenType::Assign, "=".to_string())),
+                Ok(Token::new(TokenType::Number, "5".to_string())),
+                Err(LexError::UnexpectedCharacter {
+                    found: '$',
+                    span: Span::new(10, 11, 1, 11),
+                }),
+                Ok(Token::new(TokenType::Number, "2".to_string())),
+                Ok(Token::new(TokenType::EqualEqual, "==".to_string())),
+                Ok(Token::new(TokenType::Identifier, "x".to_string())),
+                Ok(Token::new(TokenType::Assign, "=".to_string())),
+                Ok(Token::new(TokenType::Greater, ">".to_string())),
+                Ok(Token::new(TokenType::Identifier, "y".to_string())),
+                Err(LexError::UnexpectedCharacter {
+                    found: '.',
+                    span: Span::new(21, 22, 2, 8),
+                }),
+                Ok(Token::new(TokenType::Number, "5".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_strings() {
+        let tokens =
+            lex(r#""a\tb\n\\ \"q\" \u{1F600}\$" 'it\'s' "Hi ${name}!" "${a}${ {1: b}[1] }""#);
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::new(
+                    TokenType::String,
+                    "a\tb\n\\ \"q\" \u{1F600}$".to_string()
+                )),
+                Ok(Token::new(TokenType::String, "it's".to_string())),
+                Ok(Token::new(TokenType::TemplateStart, "Hi ".to_string())),
+                Ok(Token::new(TokenType::Identifier, "name".to_string())),
+                Ok(Token::new(TokenType::TemplateEnd, "!".to_string())),
+                Ok(Token::new(TokenType::TemplateStart, "".to_string())),
+                Ok(Token::new(TokenType::Identifier, "a".to_string())),
+                Ok(Token::new(TokenType::TemplateMiddle, "".to_string())),
+                Ok(Token::new(TokenType::LeftBrace, "{".to_string())),
+                Ok(Token::new(TokenType::Number, "1".to_string())),
+                Ok(Token::new(TokenType::Colon, ":".to_string())),
+                Ok(Token::new(TokenType::Identifier, "b".to_string())),
+                Ok(Token::new(TokenType::RightBrace, "}".to_string())),
+                Ok(Token::new(TokenType::LeftBracket, "[".to_string())),
+                Ok(Token::new(TokenType::Number, "1".to_string())),
+                Ok(Token::new(TokenType::RightBracket, "]".to_string())),
+                Ok(Token::new(TokenType::TemplateEnd, "".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_string_errors() {
+        let tokens = lex(r#""a\qb" 1 "\u{110000}" "\u41" "abc"#);
+
+        assert_eq!(
+            tokens,
+            vec![
+                Err(LexError::InvalidEscape {
+                    found: 'q',
+                    span: Span::new(2, 4, 1, 3),
+                }),
+                Ok(Token::new(TokenType::Number, "1".to_string())),
+                Err(LexError::InvalidUnicodeEscape {
+                    span: Span::new(10, 20, 1, 11),
+                }),
+                Err(LexError::InvalidUnicodeEscape {
+                    span: Span::new(23, 25, 1, 24),
+                }),
+                Err(LexError::UnterminatedString {
+                    span: Span::new(29, 33, 1, 30),
+                }),
+            ]
+        );
+        assert_eq!(
+            tokens[0].clone().unwrap_err().to_string(),
+            "unknown escape sequence: `\\q`"
+        );
+    }
+
+    #[test]
+    fn lexer_comments() {
+        let source = "let x = 1 // one\n/* a /* nested */ * / comment */ x / 2 //";
+
+        assert_eq!(
+            lex(source),
+            vec![
+                Ok(Token::new(TokenType::Let, "let".to_string())),
+                Ok(Token::new(TokenType::Identifier, "x".to_string())),
+                Ok(Token::new(TokenType::Assign, "=".to_string())),
+                Ok(Token::new(TokenType::Number, "1".to_string())),
+                Ok(Token::new(TokenType::Identifier, "x".to_string())),
+                Ok(Token::new(TokenType::Division, "/".to_string())),
+                Ok(Token::new(TokenType::Number, "2".to_string())),
+            ]
+        );
+
+        let comments: Vec<Token> = Lexer::with_comments(String::from(source))
+            .map(|t| t.unwrap())
+            .filter(|t| t.token_type == TokenType::Comment)
+            .collect();
+        assert_eq!(
+            comments,
+            vec![
+                Token {
+                    span: Span::new(10, 16, 1, 11),
+                    ..Token::new(TokenType::Comment, "// one".to_string())
+                },
+                Token {
+                    span: Span::new(17, 49, 2, 1),
+                    ..Token::new(
+                        TokenType::Comment,
+                        "/* a /* nested */ * / comment */".to_string()
+                    )
+                },
+                Token {
+                    span: Span::new(56, 58, 2, 40),
+                    ..Token::new(TokenType::Comment, "//".to_string())
+                },
+            ]
+        );
+
+        assert_eq!(
+            lex("1 /* open /* nested */"),
+            vec![
+                Ok(Token::new(TokenType::Number, "1".to_string())),
+                Err(LexError::UnterminatedComment {
+                    span: Span::new(2, 22, 1, 3),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_empty_source() {
+        assert_eq!(Lexer::new(String::new()).count(), 0);
+        assert_eq!(Lexer::new(String::from("  \n ")).count(), 0);
+    }
 }
@@ -1,13 +1,128 @@
-use crate::ast::{BlockStatment, Expression, Op, Precedence, Program, Statement};
-use crate::lexer::Lexer;
-use crate::token::{Token, TokenType};
+use crate::ast::{
+    BlockStatment, Expression, NamedArgument, Op, Parameter, Precedence, Program, Statement,
+};
+use crate::lexer::{LexError, Lexer};
+use crate::token::{Span, Token, TokenType};
 #[cfg(test)]
 use pretty_assertions::assert_eq as p_assert_eq;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum ParseError {
+    /// A token that can not start or continue the construct being parsed
+    UnexpectedToken { found: Token },
+    /// A specific token was required but something else was found
+    ExpectedToken { expected: TokenType, found: Token },
+    /// A `{` that was never closed before the end of the file
+    UnterminatedBlock { opened: Token },
+    /// A number literal with digits that are not valid in its base, or that does not fit in an
+    /// integer
+    InvalidNumber { found: Token },
+    /// `break` or `continue` that is not inside a loop body
+    OutsideLoop { found: Token },
+    /// The left side of `=` is not something that can be assigned to
+    InvalidAssignmentTarget { target: Box<Expression> },
+    /// Assignment to a variable declared with `const`, `target` is the variable
+    ConstAssignment { target: Box<Expression> },
+    /// A parameter without a default value after one with a default, `found` is its name
+    MissingDefault { found: Token },
+    /// A positional argument after a named one in a call
+    PositionalAfterNamed { found: Box<Expression> },
+    /// A constant and a named function with the same name in one scope, `found` is the name in
+    /// the later of the two declarations
+    ConstFunctionClash { found: Token },
+    /// The lexer could not turn the source into tokens
+    Lex(LexError),
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { found } => found.span,
+            ParseError::ExpectedToken { found, .. } => found.span,
+            ParseError::UnterminatedBlock { opened } => opened.span,
+            ParseError::InvalidNumber { found } => found.span,
+            ParseError::OutsideLoop { found } => found.span,
+            ParseError::InvalidAssignmentTarget { target } => target.span(),
+            ParseError::ConstAssignment { target } => target.span(),
+            ParseError::MissingDefault { found } => found.span,
+            ParseError::PositionalAfterNamed { found } => found.span(),
+            ParseError::ConstFunctionClash { found } => found.span,
+            ParseError::Lex(e) => e.span(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedToken { found } => {
+                write!(
+                    f,
+                    "unexpected token: {}",
+                    found.token_type.describe(&found.literal)
+                )
+            }
+            ParseError::ExpectedToken { expected, found } => write!(
+                f,
+                "expected {}, found {}",
+                expected,
+                found.token_type.describe(&found.literal)
+            ),
+            ParseError::UnterminatedBlock { .. } => {
+                write!(f, "unterminated block: `{{` is never closed")
+            }
+            ParseError::InvalidNumber { found } => {
+                write!(f, "invalid number literal: `{}`", found.literal)
+            }
+            ParseError::OutsideLoop { found } => {
+                write!(f, "{} outside of a loop", found.token_type)
+            }
+            ParseError::InvalidAssignmentTarget { target } => {
+                write!(f, "invalid assignment target: `{}`", target)
+            }
+            ParseError::ConstAssignment { target } => {
+                write!(f, "cannot assign to constant: {}", target)
+            }
+            ParseError::MissingDefault { found } => write!(
+                f,
+                "parameter `{}` needs a default value, it follows one that has a default",
+                found.literal
+            ),
+            ParseError::PositionalAfterNamed { found } => {
+                write!(f, "positional argument after named arguments: `{}`", found)
+            }
+            ParseError::ConstFunctionClash { found } => write!(
+                f,
+                "`{}` is declared as both a constant and a function in the same scope",
+                found.literal
+            ),
+            ParseError::Lex(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+pub type ParseResult<T> = Result<T, ParseError>;
+
+/// What a name in one of the parser's scopes was declared as
+#[derive(Clone, Copy, PartialEq)]
+enum Declaration {
+    Variable,
+    Constant,
+    Function,
+}
 
 pub struct Parser {
     lexer: Lexer,
     peek: Token,
     current: Token,
+    errors: Vec<ParseError>,
+    /// How many loops the current token is nested in, function bodies start again at zero
+    loop_depth: usize,
+    /// The names declared in each scope around the current token, innermost last, mapped to
+    /// what they were declared as. Used to reject assignments to constants before running
+    scopes: Vec<HashMap<String, Declaration>>,
 }
 
 impl Parser {
@@ -15,276 +130,688 @@ impl Parser {
         Self {
             lexer,
             // Starting the parser with the current token as EOF
-            current: Token {
-                token_type: TokenType::EOF,
-                literal: "".to_string(),
-            },
+            current: Token::new(TokenType::EOF, "".to_string()),
 
             // Starting the parser with the peek token as EOF
-            peek: Token {
-                token_type: TokenType::EOF,
-                literal: "".to_string(),
-            },
+            peek: Token::new(TokenType::EOF, "".to_string()),
+            errors: Vec::new(),
+            loop_depth: 0,
+            scopes: vec![HashMap::new()],
         }
     }
 
-    pub fn parse(&mut self) -> Program {
+    /// Parses the whole program, on syntax errors the parser skips ahead to the next statement
+    /// and keeps going so that every error in the source is reported at once
+    pub fn parse(&mut self) -> Result<Program, Vec<ParseError>> {
         let mut program: Program = Vec::new();
 
         while let Some(statement) = self.next() {
-            program.push(statement);
+            match statement {
+                Ok(s) => program.push(s),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.synchronize();
+
+                    // A stray closing brace has no block to end at the top level
+                    if self.current.token_type == TokenType::RightBrace {
+                        self.read();
+                    }
+                }
+            }
         }
 
-        program
+        if self.errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
     }
 
-    pub fn parse_statements(&mut self) -> Option<Statement> {
+    pub fn parse_statements(&mut self) -> ParseResult<Statement> {
+        let start = self.current.span;
         let stmt = match self.current.token_type {
-            TokenType::Let => {
-                let identifier = self.peek.clone();
-                self.read();
-                if !self.expect_n_peek(TokenType::Assign) {
-                    panic!("Expected Assign token")
+            TokenType::Let | TokenType::Const => {
+                let constant = self.current.token_type == TokenType::Const;
+                self.expect_peek(TokenType::Identifier)?;
+                let identifier = self.current.clone();
+                if constant
+                    && self.declared_here(&identifier.literal) == Some(Declaration::Function)
+                {
+                    return Err(ParseError::ConstFunctionClash { found: identifier });
                 }
+                self.expect_peek(TokenType::Assign)?;
 
                 self.read();
-                if let Some(expression) = self.parse_expression(Precedence::Lowest) {
-                    Some(Statement::Let {
+                let expression = self.parse_expression(Precedence::Lowest)?;
+                let declaration = if constant {
+                    Declaration::Constant
+                } else {
+                    Declaration::Variable
+                };
+                self.declare(&identifier.literal, declaration);
+
+                if constant {
+                    Statement::Const {
                         name: identifier.literal,
                         value: expression,
-                    })
+                        span: start.to(self.current.span),
+                    }
                 } else {
-                    // Should result in error
-                    None
+                    Statement::Let {
+                        name: identifier.literal,
+                        value: expression,
+                        span: start.to(self.current.span),
+                    }
                 }
             }
             TokenType::Return => {
                 self.read();
-                if let Some(expression) = self.parse_expression(Precedence::Lowest) {
-                    Some(Statement::Return { value: expression })
+                let expression = self.parse_expression(Precedence::Lowest)?;
+                Statement::Return {
+                    value: expression,
+                    span: start.to(self.current.span),
+                }
+            }
+            TokenType::While => {
+                self.expect_peek(TokenType::LeftParen)?;
+                self.read();
+                let condition = self.parse_expression(Precedence::Lowest)?;
+                self.expect_peek(TokenType::RightParen)?;
+                self.expect_peek(TokenType::LeftBrace)?;
+
+                Statement::While {
+                    condition,
+                    body: self.parse_loop_body()?,
+                    span: start.to(self.current.span),
+                }
+            }
+            TokenType::For => {
+                self.expect_peek(TokenType::Identifier)?;
+                let variable = self.current.literal.clone();
+                self.expect_peek(TokenType::In)?;
+                self.read();
+                let iterable = self.parse_expression(Precedence::Lowest)?;
+                self.expect_peek(TokenType::LeftBrace)?;
+
+                self.scopes
+                    .push(HashMap::from([(variable.clone(), Declaration::Variable)]));
+                let body = self.parse_loop_body();
+                self.scopes.pop();
+
+                Statement::For {
+                    variable,
+                    iterable,
+                    body: body?,
+                    span: start.to(self.current.span),
+                }
+            }
+            TokenType::Fn if self.peek.token_type == TokenType::Identifier => {
+                self.read();
+                let name = self.current.literal.clone();
+                if self.declared_here(&name) == Some(Declaration::Constant) {
+                    return Err(ParseError::ConstFunctionClash {
+                        found: self.current.clone(),
+                    });
+                }
+                self.declare(&name, Declaration::Function);
+
+                Statement::Function {
+                    name,
+                    value: self.parse_function()?,
+                    span: start.to(self.current.span),
+                }
+            }
+            TokenType::Break | TokenType::Continue => {
+                if self.loop_depth == 0 {
+                    return Err(ParseError::OutsideLoop {
+                        found: self.current.clone(),
+                    });
+                }
+
+                if self.current.token_type == TokenType::Break {
+                    Statement::Break { span: start }
                 } else {
-                    // Should result in error
-                    None
+                    Statement::Continue { span: start }
                 }
             }
             _ => {
-                if let Some(expression) = self.parse_expression(Precedence::Lowest) {
-                    Some(Statement::StatmentExpression { value: expression })
-                } else {
-                    None
+                let expression = self.parse_expression(Precedence::Lowest)?;
+                Statement::StatmentExpression {
+                    value: expression,
+                    span: start.to(self.current.span),
                 }
             }
         };
 
+        if self.peek_token_is(TokenType::SemiColon) {
+            self.read();
+        }
+
         self.read();
-        stmt
+        Ok(stmt)
     }
 
-    pub fn parse_expression(&mut self, precedence: Precedence) -> Option<Expression> {
+    pub fn parse_expression(&mut self, precedence: Precedence) -> ParseResult<Expression> {
         let mut left = match self.current.clone().token_type {
-            TokenType::Number => Expression::Number(self.current.literal.parse().unwrap()),
-            TokenType::String => Expression::String(self.current.literal.clone()),
-            TokenType::Identifier => Expression::Indentifier(self.current.clone().literal),
-            TokenType::Bool => Expression::Boolean(self.current.literal == "True".to_string()),
-            TokenType::If => self.parse_if_expressions(),
-            TokenType::LeftParen => self.parse_grouped_expresion().unwrap(),
-            TokenType::Minus | TokenType::Bang => return self.parse_prefix_expression(),
-            TokenType::Fn => self.parse_function(),
+            TokenType::Number => self.parse_number()?,
+            TokenType::String => {
+                Expression::String(self.current.literal.clone(), self.current.span)
+            }
+            TokenType::TemplateStart => self.parse_interpolated_string()?,
+            TokenType::Identifier => {
+                Expression::Indentifier(self.current.literal.clone(), self.current.span)
+            }
+            TokenType::Bool => {
+                Expression::Boolean(self.current.literal == "True", self.current.span)
+            }
+            TokenType::If => self.parse_if_expressions()?,
+            TokenType::LeftParen => self.parse_grouped_expresion()?,
+            TokenType::Minus | TokenType::Bang => self.parse_prefix_expression()?,
+            TokenType::Fn => self.parse_function()?,
+            TokenType::LeftBracket => Expression::ArrayLiteral {
+                Token: self.current.clone(),
+                Elements: self.parse_expression_list(TokenType::RightBracket)?,
+            },
+            // Blocks are only parsed after `)` or `else`, so a `{` starting an expression is
+            // always a hash literal
+            TokenType::LeftBrace => self.parse_hash_literal()?,
             _ => {
-                return None;
+                return Err(ParseError::UnexpectedToken {
+                    found: self.current.clone(),
+                });
             }
         };
 
-        // dbg!(&left);
-
         while self.peek.token_type != TokenType::EOF
             && self.peek.token_type != TokenType::SemiColon
             && precedence < self.peek_precedence()
         {
-            if let Some(expression) = self.parse_infix_expression(left.clone()) {
-                left = expression
-            }
+            left = self.parse_infix_expression(left)?;
         }
 
-        Some(left)
+        Ok(left)
     }
 
-    fn parse_function(&mut self) -> Expression {
+    /// Number literals with a fraction or an exponent are floats, all others are integers
+    fn parse_number(&self) -> ParseResult<Expression> {
+        let literal = self.current.literal.as_str();
+        let span = self.current.span;
+
+        let number = if let Some(digits) = literal.strip_prefix("0x") {
+            i64::from_str_radix(digits, 16)
+                .ok()
+                .map(|n| Expression::Integer(n, span))
+        } else if let Some(digits) = literal.strip_prefix("0b") {
+            i64::from_str_radix(digits, 2)
+                .ok()
+                .map(|n| Expression::Integer(n, span))
+        } else if literal.contains(['.', 'e', 'E']) {
+            literal.parse().ok().map(|n| Expression::Float(n, span))
+        } else {
+            literal.parse().ok().map(|n| Expression::Integer(n, span))
+        };
+
+        number.ok_or_else(|| ParseError::InvalidNumber {
+            found: self.current.clone(),
+        })
+    }
+
+    /// Parses `{key: value, ...}`, the current token has to be the opening brace
+    fn parse_hash_literal(&mut self) -> ParseResult<Expression> {
         let token = self.current.clone();
-        if !self.expect_n_peek(TokenType::LeftParen) {
-            panic!("Syntax error, got: {:#?}", self.peek)
+        let mut pairs = Vec::new();
+
+        while !self.peek_token_is(TokenType::RightBrace) {
+            self.read();
+            let key = self.parse_expression(Precedence::Lowest)?;
+            self.expect_peek(TokenType::Colon)?;
+
+            self.read();
+            let value = self.parse_expression(Precedence::Lowest)?;
+            pairs.push((key, value));
+
+            if !self.peek_token_is(TokenType::RightBrace) {
+                self.expect_peek(TokenType::Comma)?;
+            }
         }
         self.read();
 
-        let params = self.pase_fn_parameters();
+        Ok(Expression::HashLiteral {
+            Token: token,
+            Pairs: pairs,
+        })
+    }
+
+    /// Parses `"text ${expression} text"`, the current token has to be the text before the first
+    /// interpolation. Empty text between interpolations is left out of the parts
+    fn parse_interpolated_string(&mut self) -> ParseResult<Expression> {
+        let token = self.current.clone();
+        let mut parts = Vec::new();
+
+        loop {
+            if !self.current.literal.is_empty() {
+                parts.push(Expression::String(
+                    self.current.literal.clone(),
+                    self.current.span,
+                ));
+            }
+            if self.current.token_type == TokenType::TemplateEnd {
+                break;
+            }
+
+            self.read();
+            parts.push(self.parse_expression(Precedence::Lowest)?);
+
+            if !self.peek_token_is(TokenType::TemplateMiddle) {
+                self.expect_peek(TokenType::TemplateEnd)?;
+            } else {
+                self.read();
+            }
+        }
+
+        Ok(Expression::InterpolatedString {
+            Token: token,
+            Parts: parts,
+        })
+    }
 
+    /// Parses the block of a loop, where `break` and `continue` are allowed
+    fn parse_loop_body(&mut self) -> ParseResult<BlockStatment> {
+        self.loop_depth += 1;
         let body = self.parse_block_statment();
+        self.loop_depth -= 1;
 
-        Expression::FunctionLiteral {
+        body
+    }
+
+    /// Parses the parameters and body of a function, the current token has to be `fn` or the name
+    /// of a function declaration
+    fn parse_function(&mut self) -> ParseResult<Expression> {
+        let token = self.current.clone();
+        self.expect_peek(TokenType::LeftParen)?;
+        self.read();
+
+        // A loop around the function does not make `break` valid inside of it, not even in the
+        // default values of its parameters
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let function = self.parse_function_rest();
+        self.loop_depth = loop_depth;
+        let (params, body) = function?;
+
+        Ok(Expression::FunctionLiteral {
             Token: token,
             Parameters: params,
             Body: body,
-        }
+        })
+    }
+
+    /// Parses the parameters and the body of a function, starting at its first parameter
+    fn parse_function_rest(&mut self) -> ParseResult<(Option<Vec<Parameter>>, BlockStatment)> {
+        let params = self.pase_fn_parameters()?;
+
+        let scope = params
+            .iter()
+            .flatten()
+            .map(|param| (param.name.clone(), Declaration::Variable))
+            .collect();
+        self.scopes.push(scope);
+        let body = self.parse_block_statment();
+        self.scopes.pop();
+
+        Ok((params, body?))
     }
 
-    fn pase_fn_parameters(&mut self) -> Option<Vec<Expression>> {
-        let mut identifiers = Vec::<Expression>::new();
+    fn pase_fn_parameters(&mut self) -> ParseResult<Option<Vec<Parameter>>> {
+        let mut parameters = Vec::<Parameter>::new();
 
         if self.current.token_type == TokenType::RightParen {
-            self.read();
-            return None;
+            self.expect_peek(TokenType::LeftBrace)?;
+            return Ok(None);
         }
 
-        identifiers.push(Expression::Indentifier(self.current.literal.clone()));
+        loop {
+            let token = self.current.clone();
+            let parameter = self.parse_fn_parameter()?;
+            let required = parameter.default.is_none() && !parameter.rest;
+            if required && parameters.iter().any(|p| p.default.is_some()) {
+                return Err(ParseError::MissingDefault { found: token });
+            }
 
-        while self.peek_token_is(TokenType::Comma) {
+            let rest = parameter.rest;
+            parameters.push(parameter);
+
+            if !self.peek_token_is(TokenType::Comma) {
+                break;
+            }
             self.read();
+            // A rest parameter has to be the last one, only a trailing comma can follow it
+            if rest || self.peek_token_is(TokenType::RightParen) {
+                break;
+            }
             self.read();
-            if let Some(ident_exp) = self.parse_expression(Precedence::Lowest) {
-                if !matches!(ident_exp, Expression::Indentifier(..)) {
-                    panic!("Didnt get identifier")
-                }
+        }
 
-                identifiers.push(ident_exp)
-            }
+        self.expect_peek(TokenType::RightParen)?;
+        self.expect_peek(TokenType::LeftBrace)?;
+
+        Ok(Some(parameters))
+    }
+
+    /// Parses `name`, `name = default` or `...name`, starting at the current token
+    fn parse_fn_parameter(&mut self) -> ParseResult<Parameter> {
+        let rest = self.current.token_type == TokenType::Ellipsis;
+        if rest {
+            self.read();
         }
 
-        if !self.expect_n_peek(TokenType::RightParen) {
-            panic!("Syntax error, {:#?}", &self.peek)
+        if self.current.token_type != TokenType::Identifier {
+            return Err(ParseError::ExpectedToken {
+                expected: TokenType::Identifier,
+                found: self.current.clone(),
+            });
         }
-        self.read();
+        let name = self.current.clone();
+
+        let default = if !rest && self.peek_token_is(TokenType::Assign) {
+            self.read();
+            self.read();
+            Some(self.parse_expression(Precedence::Lowest)?)
+        } else {
+            None
+        };
 
-        Some(identifiers)
+        Ok(Parameter {
+            name: name.literal,
+            default,
+            rest,
+            span: name.span,
+        })
     }
 
-    pub fn parse_if_expressions(&mut self) -> Expression {
-        if !self.expect_n_peek(TokenType::LeftParen) {
-            panic!("Syntax error")
-        }
+    pub fn parse_if_expressions(&mut self) -> ParseResult<Expression> {
+        let token = self.current.clone();
+        self.expect_peek(TokenType::LeftParen)?;
 
         self.read();
 
-        let condition = self.parse_expression(Precedence::Lowest);
+        let condition = self.parse_expression(Precedence::Lowest)?;
 
-        self.read();
-        if !self.expect_n_peek(TokenType::LeftBrace) {
-            panic!("Syntax error, {:#?}", &self.current)
-        }
+        self.expect_peek(TokenType::RightParen)?;
+        self.expect_peek(TokenType::LeftBrace)?;
 
-        let consequence = self.parse_block_statment();
+        let consequence = self.parse_block_statment()?;
 
         let alternative: Option<BlockStatment> = if self.peek_token_is(TokenType::Else) {
             self.read();
-            if !self.expect_n_peek(TokenType::LeftBrace) {
-                panic!("Syntax error")
-            }
+            self.expect_peek(TokenType::LeftBrace)?;
 
-            let alternative = self.parse_block_statment();
+            let alternative = self.parse_block_statment()?;
 
             Some(alternative)
         } else {
             None
         };
 
-        Expression::IfExpression {
-            Token: Token {
-                token_type: TokenType::If,
-                literal: "if".to_string(),
-            },
-            Condition: Box::new(condition.expect("Syntax error in condition")),
+        Ok(Expression::IfExpression {
+            Token: token,
+            Condition: Box::new(condition),
             Consequence: consequence,
             Alternative: alternative,
-        }
+        })
     }
 
-    pub fn parse_block_statment(&mut self) -> BlockStatment {
+    /// Parses the statements of a block, the current token has to be the opening brace. Errors in
+    /// the statements are recorded and skipped, only a missing closing brace fails the block
+    pub fn parse_block_statment(&mut self) -> ParseResult<BlockStatment> {
+        let opened = self.current.clone();
         let mut block = Vec::<Statement>::new();
 
         self.read();
+        self.scopes.push(HashMap::new());
 
-        while self.current.token_type != TokenType::RightBrace
-            && self.current.token_type != TokenType::EOF
-        {
-            if let Some(stmt) = self.parse_statements() {
-                block.push(stmt)
+        while self.current.token_type != TokenType::RightBrace {
+            if self.current.token_type == TokenType::EOF {
+                self.scopes.pop();
+                return Err(ParseError::UnterminatedBlock { opened });
+            }
+
+            match self.parse_statements() {
+                Ok(stmt) => block.push(stmt),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.synchronize();
+                }
             }
         }
 
-        BlockStatment { Statement: block }
+        self.scopes.pop();
+        Ok(BlockStatment { Statement: block })
     }
 
-    pub fn parse_grouped_expresion(&mut self) -> Option<Expression> {
+    fn declare(&mut self, name: &str, declaration: Declaration) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), declaration);
+        }
+    }
+
+    /// What `name` was declared as in the innermost scope, without looking at the ones around it
+    fn declared_here(&self, name: &str) -> Option<Declaration> {
+        self.scopes.last()?.get(name).copied()
+    }
+
+    /// Whether `name` refers to a constant in the current scope, names the parser has not seen
+    /// declared are not
+    fn is_constant(&self, name: &str) -> bool {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .is_some_and(|declaration| *declaration == Declaration::Constant)
+    }
+
+    pub fn parse_grouped_expresion(&mut self) -> ParseResult<Expression> {
         self.read();
-        let expression = self.parse_expression(Precedence::Lowest);
+        let expression = self.parse_expression(Precedence::Lowest)?;
 
-        self.expect_n_peek(TokenType::RightParen);
+        self.expect_peek(TokenType::RightParen)?;
 
-        expression
+        Ok(expression)
     }
 
-    pub fn parse_prefix_expression(&mut self) -> Option<Expression> {
+    pub fn parse_prefix_expression(&mut self) -> ParseResult<Expression> {
         let current = self.current.clone();
         self.read();
 
-        Some(Expression::PrefixExpression {
+        Ok(Expression::PrefixExpression {
             Token: current.clone(),
             Op: Op::token(&current.token_type),
-            Right: Box::new(self.parse_expression(Precedence::Lowest)),
+            Right: Box::new(Some(self.parse_expression(Precedence::Prefix)?)),
         })
     }
 
-    pub fn parse_infix_expression(&mut self, left: Expression) -> Option<Expression> {
+    pub fn parse_infix_expression(&mut self, left: Expression) -> ParseResult<Expression> {
         match self.peek.token_type {
             TokenType::LeftParen => {
                 self.read();
-                if self.peek.token_type == TokenType::RightParen {
-                    Some(Expression::CallExpression {
-                        Token: self.current.clone(),
-                        Function: Box::new(left),
-                        Arguments: None,
-                    })
-                } else {
-                    let mut args = Vec::<Expression>::new();
+                let expression_token = self.current.clone();
 
-                    let expression_token = self.current.clone();
-                    self.read();
+                let (args, named) = self.parse_call_arguments()?;
 
-                    args.push(self.parse_expression(Precedence::Lowest).unwrap());
+                Ok(Expression::CallExpression {
+                    Token: expression_token,
+                    Function: Box::new(left),
+                    Arguments: if args.is_empty() { None } else { Some(args) },
+                    NamedArguments: named,
+                })
+            }
+            TokenType::LeftBracket => {
+                self.read();
+                let expression_token = self.current.clone();
 
-                    while self.peek_token_is(TokenType::Comma) {
-                        self.read();
-                        self.read();
-                        if let Some(expr) = self.parse_expression(Precedence::Lowest) {
-                            args.push(expr)
-                        }
-                    }
-                    self.read();
+                self.read();
+                let index = self.parse_expression(Precedence::Lowest)?;
+                self.expect_peek(TokenType::RightBracket)?;
 
-                    Some(Expression::CallExpression {
-                        Token: expression_token,
-                        Function: Box::new(left),
-                        Arguments: Some(args),
-                    })
+                Ok(Expression::IndexExpression {
+                    Token: expression_token,
+                    Left: Box::new(left),
+                    Index: Box::new(index),
+                })
+            }
+            TokenType::Assign
+            | TokenType::PlusEqual
+            | TokenType::MinusEqual
+            | TokenType::StarEqual
+            | TokenType::SlashEqual => {
+                match assignment_root(&left) {
+                    Some(root @ Expression::Indentifier(name, _)) if self.is_constant(name) => {
+                        return Err(ParseError::ConstAssignment {
+                            target: Box::new(root.clone()),
+                        });
+                    }
+                    Some(_) => {}
+                    None => {
+                        return Err(ParseError::InvalidAssignmentTarget {
+                            target: Box::new(left),
+                        })
+                    }
                 }
+
+                self.read();
+                let token = self.current.clone();
+
+                // Parsed with the lowest precedence so `a = b = 1` assigns right to left
+                self.read();
+                let value = self.parse_expression(Precedence::Lowest)?;
+
+                Ok(Expression::AssignExpression {
+                    Op: Op::token(&token.token_type),
+                    Token: token,
+                    Target: Box::new(left),
+                    Value: Box::new(value),
+                })
             }
             _ => {
-                let precedence = self.peek_precedence();
+                let mut precedence = self.peek_precedence();
                 let last_peek_token = self.peek.clone();
 
+                // `**` is right associative, so the right side takes any further `**` with it
+                if precedence == Precedence::Power {
+                    precedence = Precedence::Prefix;
+                }
+
                 self.read();
                 self.read();
-                let right = self.parse_expression(precedence);
+                let right = self.parse_expression(precedence)?;
 
-                Some(Expression::InfixExpression {
+                Ok(Expression::InfixExpression {
                     Left: Box::new(left),
                     Op: Op::token(&last_peek_token.token_type),
                     Token: last_peek_token,
-                    Right: Box::new(right),
+                    Right: Box::new(Some(right)),
                 })
             }
         }
     }
 
+    /// Parses comma separated expressions up to the `end` token, the current token has to be the
+    /// one opening the list. Leaves the parser on the `end` token
+    fn parse_expression_list(&mut self, end: TokenType) -> ParseResult<Vec<Expression>> {
+        let mut list = Vec::<Expression>::new();
+
+        if self.peek_token_is(end.clone()) {
+            self.read();
+            return Ok(list);
+        }
+
+        self.read();
+        list.push(self.parse_expression(Precedence::Lowest)?);
+
+        while self.peek_token_is(TokenType::Comma) {
+            self.read();
+            // Like every bracketed list, it can end with a comma
+            if self.peek_token_is(end.clone()) {
+                break;
+            }
+            self.read();
+            list.push(self.parse_expression(Precedence::Lowest)?);
+        }
+        self.expect_peek(end)?;
+
+        Ok(list)
+    }
+
+    /// Parses the arguments of a call up to the closing `)`, the current token has to be the
+    /// opening one. Returns the positional arguments and then the named ones
+    fn parse_call_arguments(&mut self) -> ParseResult<(Vec<Expression>, Vec<NamedArgument>)> {
+        let mut positional = Vec::new();
+        let mut named = Vec::new();
+
+        if self.peek_token_is(TokenType::RightParen) {
+            self.read();
+            return Ok((positional, named));
+        }
+
+        loop {
+            self.read();
+
+            if self.current.token_type == TokenType::Identifier
+                && self.peek_token_is(TokenType::Colon)
+            {
+                let name = self.current.clone();
+                self.read();
+                self.read();
+                named.push((name, self.parse_expression(Precedence::Lowest)?));
+            } else {
+                let argument = self.parse_expression(Precedence::Lowest)?;
+                if !named.is_empty() {
+                    return Err(ParseError::PositionalAfterNamed {
+                        found: Box::new(argument),
+                    });
+                }
+                positional.push(argument);
+            }
+
+            if !self.peek_token_is(TokenType::Comma) {
+                break;
+            }
+            self.read();
+            if self.peek_token_is(TokenType::RightParen) {
+                break;
+            }
+        }
+        self.expect_peek(TokenType::RightParen)?;
+
+        Ok((positional, named))
+    }
+
+    /// Skips tokens until the start of the next statement, so parsing can continue after an
+    /// error. Stops in front of a closing brace so the enclosing block can still be closed
+    fn synchronize(&mut self) {
+        loop {
+            match self.current.token_type {
+                TokenType::EOF | TokenType::RightBrace => return,
+                TokenType::SemiColon => {
+                    self.read();
+                    return;
+                }
+                _ => self.read(),
+            }
+
+            if matches!(
+                self.current.token_type,
+                TokenType::Let
+                    | TokenType::Const
+                    | TokenType::Fn
+                    | TokenType::Return
+                    | TokenType::While
+                    | TokenType::For
+            ) {
+                return;
+            }
+        }
+    }
+
     /// Looks at the precedence of the next token type
     fn peek_precedence(&mut self) -> Precedence {
         Precedence::get_precedence(&self.peek.token_type)
@@ -292,44 +819,58 @@ impl Parser {
 
     /// Given a token token type, returns a bool depending if the next token type is of that type
     fn peek_token_is(&mut self, t: TokenType) -> bool {
-        if self.peek.token_type == t {
-            true
-        } else {
-            false
-        }
+        self.peek.token_type == t
     }
 
-    /// Given a TokenType returns a bool if its the correct token type, this fuction eats the next
-    /// token if it is the expected type
-    fn expect_n_peek(&mut self, token_type: TokenType) -> bool {
-        if self.peek_token_is(token_type) {
+    /// Given a TokenType eats the next token if it is of that type, otherwise returns an error
+    /// describing what was found instead
+    fn expect_peek(&mut self, token_type: TokenType) -> ParseResult<()> {
+        if self.peek_token_is(token_type.clone()) {
             self.read();
-            true
+            Ok(())
         } else {
-            false
+            Err(ParseError::ExpectedToken {
+                expected: token_type,
+                found: self.peek.clone(),
+            })
         }
     }
 
-    /// Reads and eats the next token
+    /// Reads and eats the next token, errors from the lexer are recorded and the offending
+    /// characters skipped
     pub fn read(&mut self) {
         self.current = self.peek.clone();
-        self.peek = if let Some(token) = self.lexer.next() {
-            token
-        } else {
-            Token {
-                token_type: TokenType::EOF,
-                literal: "".to_string(),
+        self.peek = loop {
+            match self.lexer.next() {
+                Some(Ok(token)) => break token,
+                Some(Err(e)) => self.errors.push(ParseError::Lex(e)),
+                None => {
+                    break Token {
+                        span: self.lexer.location(),
+                        ..Token::new(TokenType::EOF, "".to_string())
+                    }
+                }
             }
         }
     }
 
-    /// Returns the next token
-    pub fn next(&mut self) -> Option<Statement> {
+    /// Parses the next statement, returns None once the end of the file is reached
+    pub fn next(&mut self) -> Option<ParseResult<Statement>> {
         if self.current.token_type == TokenType::EOF {
             return None;
         }
 
-        self.parse_statements()
+        Some(self.parse_statements())
+    }
+}
+
+/// Returns the variable that is updated when assigning to `expression`, None when it can not be
+/// on the left side of `=`. Only variables and elements of collections stored in a variable can
+fn assignment_root(expression: &Expression) -> Option<&Expression> {
+    match expression {
+        Expression::Indentifier(..) => Some(expression),
+        Expression::IndexExpression { Left: left, .. } => assignment_root(left),
+        _ => None,
     }
 }
 
@@ -339,49 +880,75 @@ mod tests {
     use crate::{
         ast::{self, Expression},
         lexer,
+        token::Span,
     };
 
     use super::*;
+
+    /// Formats `value` with every span left out, so tests can compare what was parsed without
+    /// spelling out where each node was found
+    fn without_spans(value: &impl std::fmt::Debug) -> String {
+        let mut out = String::new();
+        // Indentation of the span that is being left out
+        let mut skipping = None;
+        for line in format!("{:#?}", value).lines() {
+            let indent = line.len() - line.trim_start().len();
+            match skipping {
+                // The closing brace of the span
+                Some(depth) if depth == indent => skipping = None,
+                Some(_) => {}
+                None if line.ends_with("Span {") => skipping = Some(indent),
+                None => {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+        }
+
+        out
+    }
+
+    fn parameter(name: &str) -> Parameter {
+        Parameter {
+            name: name.to_string(),
+            default: None,
+            rest: false,
+            span: Span::default(),
+        }
+    }
     #[test]
     fn parse_call_expressionss() {
         let lexer = lexer::Lexer::new(String::from("add(1, 2 * 3, 4 + 5)"));
         let mut parser = Parser::new(lexer);
         parser.read();
         parser.read();
-        let program = parser.parse();
+        let program = parser.parse().unwrap();
 
         let expected_program: ast::Program = Vec::from([Statement::StatmentExpression {
             value: Expression::CallExpression {
-                Token: Token {
-                    token_type: TokenType::LeftParen,
-                    literal: "(".to_string(),
-                },
-                Function: Box::new(Expression::Indentifier("add".to_string())),
+                Token: Token::new(TokenType::LeftParen, "(".to_string()),
+                Function: Box::new(Expression::Indentifier("add".to_string(), Span::default())),
                 Arguments: Some(vec![
-                    Expression::Number(1.00),
+                    Expression::Integer(1, Span::default()),
                     Expression::InfixExpression {
-                        Token: Token {
-                            token_type: TokenType::Star,
-                            literal: "*".to_string(),
-                        },
-                        Left: Box::new(Expression::Number(2.00)),
+                        Token: Token::new(TokenType::Star, "*".to_string()),
+                        Left: Box::new(Expression::Integer(2, Span::default())),
                         Op: Op::Multiply,
-                        Right: Box::new(Some(Expression::Number(3.00))),
+                        Right: Box::new(Some(Expression::Integer(3, Span::default()))),
                     },
                     Expression::InfixExpression {
-                        Token: Token {
-                            token_type: TokenType::Addition,
-                            literal: "+".to_string(),
-                        },
-                        Left: Box::new(Expression::Number(4.00)),
+                        Token: Token::new(TokenType::Addition, "+".to_string()),
+                        Left: Box::new(Expression::Integer(4, Span::default())),
                         Op: Op::Add,
-                        Right: Box::new(Some(Expression::Number(5.00))),
+                        Right: Box::new(Some(Expression::Integer(5, Span::default()))),
                     },
                 ]),
+                NamedArguments: vec![],
             },
+            span: Span::default(),
         }]);
 
-        p_assert_eq!(program, expected_program)
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program))
     }
 
     #[test]
@@ -390,20 +957,390 @@ mod tests {
         let mut parser = Parser::new(lexer);
         parser.read();
         parser.read();
-        let program = parser.parse();
+        let program = parser.parse().unwrap();
 
         let expected_program: ast::Program = Vec::from([Statement::StatmentExpression {
             value: Expression::CallExpression {
-                Token: Token {
-                    token_type: TokenType::LeftParen,
-                    literal: "(".to_string(),
-                },
-                Function: Box::new(Expression::Indentifier("add".to_string())),
+                Token: Token::new(TokenType::LeftParen, "(".to_string()),
+                Function: Box::new(Expression::Indentifier("add".to_string(), Span::default())),
                 Arguments: None,
+                NamedArguments: vec![],
+            },
+            span: Span::default(),
+        }]);
+
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program))
+    }
+
+    #[test]
+    fn parse_array_and_index_expression() {
+        let lexer = lexer::Lexer::new(String::from("[1, 2 * 3][a + 1]; []"));
+        let mut parser = Parser::new(lexer);
+        parser.read();
+        parser.read();
+        let program = parser.parse().unwrap();
+
+        let expected_program: ast::Program = Vec::from([
+            Statement::StatmentExpression {
+                value: Expression::IndexExpression {
+                    Token: Token::new(TokenType::LeftBracket, "[".to_string()),
+                    Left: Box::new(Expression::ArrayLiteral {
+                        Token: Token::new(TokenType::LeftBracket, "[".to_string()),
+                        Elements: vec![
+                            Expression::Integer(1, Span::default()),
+                            Expression::InfixExpression {
+                                Token: Token::new(TokenType::Star, "*".to_string()),
+                                Left: Box::new(Expression::Integer(2, Span::default())),
+                                Op: Op::Multiply,
+                                Right: Box::new(Some(Expression::Integer(3, Span::default()))),
+                            },
+                        ],
+                    }),
+                    Index: Box::new(Expression::InfixExpression {
+                        Token: Token::new(TokenType::Addition, "+".to_string()),
+                        Left: Box::new(Expression::Indentifier("a".to_string(), Span::default())),
+                        Op: Op::Add,
+                        Right: Box::new(Some(Expression::Integer(1, Span::default()))),
+                    }),
+                },
+                span: Span::default(),
+            },
+            Statement::StatmentExpression {
+                value: Expression::ArrayLiteral {
+                    Token: Token::new(TokenType::LeftBracket, "[".to_string()),
+                    Elements: vec![],
+                },
+                span: Span::default(),
+            },
+        ]);
+
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program))
+    }
+
+    #[test]
+    fn parse_hash_literal_and_assignment() {
+        let lexer = lexer::Lexer::new(String::from("{\"a\": 1, True: b,} h[\"a\"] = {}"));
+        let mut parser = Parser::new(lexer);
+        parser.read();
+        parser.read();
+        let program = parser.parse().unwrap();
+
+        let expected_program: ast::Program = Vec::from([
+            Statement::StatmentExpression {
+                value: Expression::HashLiteral {
+                    Token: Token::new(TokenType::LeftBrace, "{".to_string()),
+                    Pairs: vec![
+                        (
+                            Expression::String("a".to_string(), Span::default()),
+                            Expression::Integer(1, Span::default()),
+                        ),
+                        (
+                            Expression::Boolean(true, Span::default()),
+                            Expression::Indentifier("b".to_string(), Span::default()),
+                        ),
+                    ],
+                },
+                span: Span::default(),
+            },
+            Statement::StatmentExpression {
+                value: Expression::AssignExpression {
+                    Token: Token::new(TokenType::Assign, "=".to_string()),
+                    Target: Box::new(Expression::IndexExpression {
+                        Token: Token::new(TokenType::LeftBracket, "[".to_string()),
+                        Left: Box::new(Expression::Indentifier("h".to_string(), Span::default())),
+                        Index: Box::new(Expression::String("a".to_string(), Span::default())),
+                    }),
+                    Op: Op::Assign,
+                    Value: Box::new(Expression::HashLiteral {
+                        Token: Token::new(TokenType::LeftBrace, "{".to_string()),
+                        Pairs: vec![],
+                    }),
+                },
+                span: Span::default(),
+            },
+        ]);
+
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program))
+    }
+
+    #[test]
+    fn parse_trailing_commas() {
+        for (input, expected) in [
+            ("[1, 2,]", "[1, 2]"),
+            ("f(1,)", "f(1)"),
+            ("f(1, a: 2,)", "f(1, a: 2)"),
+            ("{\"a\": 1,}", "{a: 1}"),
+            ("fn(a, b,) {}", "fn(a, b) {  }"),
+            ("fn(...rest,) {}", "fn(...rest) {  }"),
+        ] {
+            let lexer = lexer::Lexer::new(String::from(input));
+            let mut parser = Parser::new(lexer);
+            parser.read();
+            parser.read();
+            let program = parser.parse().unwrap();
+
+            p_assert_eq!(program[0].to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn parse_interpolated_string() {
+        let lexer = lexer::Lexer::new(String::from(r#""Hi ${name}, ${a + 1}${b}!""#));
+        let mut parser = Parser::new(lexer);
+        parser.read();
+        parser.read();
+        let program = parser.parse().unwrap();
+
+        let expected_program: ast::Program = Vec::from([Statement::StatmentExpression {
+            value: Expression::InterpolatedString {
+                Token: Token::new(TokenType::TemplateStart, "Hi ".to_string()),
+                Parts: vec![
+                    Expression::String("Hi ".to_string(), Span::default()),
+                    Expression::Indentifier("name".to_string(), Span::default()),
+                    Expression::String(", ".to_string(), Span::default()),
+                    Expression::InfixExpression {
+                        Token: Token::new(TokenType::Addition, "+".to_string()),
+                        Left: Box::new(Expression::Indentifier("a".to_string(), Span::default())),
+                        Op: Op::Add,
+                        Right: Box::new(Some(Expression::Integer(1, Span::default()))),
+                    },
+                    Expression::Indentifier("b".to_string(), Span::default()),
+                    Expression::String("!".to_string(), Span::default()),
+                ],
+            },
+            span: Span::default(),
+        }]);
+
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program));
+        p_assert_eq!(program[0].to_string(), "Hi ${name}, ${a+1}${b}!");
+    }
+
+    #[test]
+    fn parse_function_declaration() {
+        let lexer = lexer::Lexer::new(String::from("fn add(a, b) { a + b } fn(x) { x }"));
+        let mut parser = Parser::new(lexer);
+        parser.read();
+        parser.read();
+        let program = parser.parse().unwrap();
+
+        let expected_program: ast::Program = Vec::from([
+            Statement::Function {
+                name: "add".to_string(),
+                value: Expression::FunctionLiteral {
+                    Token: Token::new(TokenType::Identifier, "add".to_string()),
+                    Parameters: Some(vec![parameter("a"), parameter("b")]),
+                    Body: BlockStatment {
+                        Statement: vec![Statement::StatmentExpression {
+                            value: Expression::InfixExpression {
+                                Token: Token::new(TokenType::Addition, "+".to_string()),
+                                Left: Box::new(Expression::Indentifier(
+                                    "a".to_string(),
+                                    Span::default(),
+                                )),
+                                Op: Op::Add,
+                                Right: Box::new(Some(Expression::Indentifier(
+                                    "b".to_string(),
+                                    Span::default(),
+                                ))),
+                            },
+                            span: Span::default(),
+                        }],
+                    },
+                },
+                span: Span::default(),
+            },
+            Statement::StatmentExpression {
+                value: Expression::FunctionLiteral {
+                    Token: Token::new(TokenType::Fn, "fn".to_string()),
+                    Parameters: Some(vec![parameter("x")]),
+                    Body: BlockStatment {
+                        Statement: vec![Statement::StatmentExpression {
+                            value: Expression::Indentifier("x".to_string(), Span::default()),
+                            span: Span::default(),
+                        }],
+                    },
+                },
+                span: Span::default(),
             },
+        ]);
+
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program));
+        p_assert_eq!(program[0].to_string(), "fn add(a, b) { a+b }");
+    }
+
+    #[test]
+    fn parse_function_parameters() {
+        let lexer = lexer::Lexer::new(String::from("fn(a, b = a + 1, ...rest) {}"));
+        let mut parser = Parser::new(lexer);
+        parser.read();
+        parser.read();
+        let program = parser.parse().unwrap();
+
+        let expected_program: ast::Program = Vec::from([Statement::StatmentExpression {
+            value: Expression::FunctionLiteral {
+                Token: Token::new(TokenType::Fn, "fn".to_string()),
+                Parameters: Some(vec![
+                    parameter("a"),
+                    Parameter {
+                        default: Some(Expression::InfixExpression {
+                            Token: Token::new(TokenType::Addition, "+".to_string()),
+                            Left: Box::new(Expression::Indentifier(
+                                "a".to_string(),
+                                Span::default(),
+                            )),
+                            Op: Op::Add,
+                            Right: Box::new(Some(Expression::Integer(1, Span::default()))),
+                        }),
+                        ..parameter("b")
+                    },
+                    Parameter {
+                        rest: true,
+                        ..parameter("rest")
+                    },
+                ]),
+                Body: BlockStatment { Statement: vec![] },
+            },
+            span: Span::default(),
+        }]);
+
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program));
+        p_assert_eq!(program[0].to_string(), "fn(a, b = a+1, ...rest) {  }");
+    }
+
+    #[test]
+    fn parse_named_arguments() {
+        let lexer = lexer::Lexer::new(String::from("connect(\"db\", port: 80, tls: True)"));
+        let mut parser = Parser::new(lexer);
+        parser.read();
+        parser.read();
+        let program = parser.parse().unwrap();
+
+        let expected_program: ast::Program = Vec::from([Statement::StatmentExpression {
+            value: Expression::CallExpression {
+                Token: Token::new(TokenType::LeftParen, "(".to_string()),
+                Function: Box::new(Expression::Indentifier(
+                    "connect".to_string(),
+                    Span::default(),
+                )),
+                Arguments: Some(vec![Expression::String("db".to_string(), Span::default())]),
+                NamedArguments: vec![
+                    (
+                        Token::new(TokenType::Identifier, "port".to_string()),
+                        Expression::Integer(80, Span::default()),
+                    ),
+                    (
+                        Token::new(TokenType::Identifier, "tls".to_string()),
+                        Expression::Boolean(true, Span::default()),
+                    ),
+                ],
+            },
+            span: Span::default(),
         }]);
 
-        p_assert_eq!(program, expected_program)
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program));
+        p_assert_eq!(program[0].to_string(), "connect(db, port: 80, tls: true)");
+    }
+
+    #[test]
+    fn parse_assignment() {
+        let lexer = lexer::Lexer::new(String::from("x = y = 1; x -= 2"));
+        let mut parser = Parser::new(lexer);
+        parser.read();
+        parser.read();
+        let program = parser.parse().unwrap();
+
+        let expected_program: ast::Program = Vec::from([
+            Statement::StatmentExpression {
+                value: Expression::AssignExpression {
+                    Token: Token::new(TokenType::Assign, "=".to_string()),
+                    Target: Box::new(Expression::Indentifier("x".to_string(), Span::default())),
+                    Op: Op::Assign,
+                    Value: Box::new(Expression::AssignExpression {
+                        Token: Token::new(TokenType::Assign, "=".to_string()),
+                        Target: Box::new(Expression::Indentifier("y".to_string(), Span::default())),
+                        Op: Op::Assign,
+                        Value: Box::new(Expression::Integer(1, Span::default())),
+                    }),
+                },
+                span: Span::default(),
+            },
+            Statement::StatmentExpression {
+                value: Expression::AssignExpression {
+                    Token: Token::new(TokenType::MinusEqual, "-=".to_string()),
+                    Target: Box::new(Expression::Indentifier("x".to_string(), Span::default())),
+                    Op: Op::Subtract,
+                    Value: Box::new(Expression::Integer(2, Span::default())),
+                },
+                span: Span::default(),
+            },
+        ]);
+
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program));
+        p_assert_eq!(program[1].to_string(), "x -= 2");
+    }
+
+    #[test]
+    fn parse_const() {
+        let lexer = lexer::Lexer::new(String::from(
+            "const limit = 10
+             let f = fn(limit) { limit = 1 }
+             let g = fn() { let limit = 2; limit += 1 }",
+        ));
+        let mut parser = Parser::new(lexer);
+        parser.read();
+        parser.read();
+        let program = parser.parse().unwrap();
+
+        p_assert_eq!(
+            without_spans(&program[0]),
+            without_spans(&Statement::Const {
+                name: "limit".to_string(),
+                value: Expression::Integer(10, Span::default()),
+                span: Span::default(),
+            })
+        );
+        p_assert_eq!(program[0].to_string(), "const limit = 10");
+    }
+
+    #[test]
+    fn parse_loops() {
+        let lexer = lexer::Lexer::new(String::from(
+            "while (x < 3) { break }
+             for item in items { continue; }",
+        ));
+        let mut parser = Parser::new(lexer);
+        parser.read();
+        parser.read();
+        let program = parser.parse().unwrap();
+
+        let expected_program: ast::Program = Vec::from([
+            Statement::While {
+                condition: Expression::InfixExpression {
+                    Token: Token::new(TokenType::Less, "<".to_string()),
+                    Left: Box::new(Expression::Indentifier("x".to_string(), Span::default())),
+                    Op: Op::LessThan,
+                    Right: Box::new(Some(Expression::Integer(3, Span::default()))),
+                },
+                body: BlockStatment {
+                    Statement: vec![Statement::Break {
+                        span: Span::default(),
+                    }],
+                },
+                span: Span::default(),
+            },
+            Statement::For {
+                variable: "item".to_string(),
+                iterable: Expression::Indentifier("items".to_string(), Span::default()),
+                body: BlockStatment {
+                    Statement: vec![Statement::Continue {
+                        span: Span::default(),
+                    }],
+                },
+                span: Span::default(),
+            },
+        ]);
+
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program))
     }
 
     #[test]
@@ -412,38 +1349,41 @@ mod tests {
         let mut parser = Parser::new(lexer);
         parser.read();
         parser.read();
-        let program = parser.parse();
+        let program = parser.parse().unwrap();
 
         let expected_program: ast::Program = Vec::from([Statement::StatmentExpression {
             value: Expression::FunctionLiteral {
-                Token: Token {
-                    token_type: TokenType::Fn,
-                    literal: "fn".to_string(),
-                },
+                Token: Token::new(TokenType::Fn, "fn".to_string()),
                 Parameters: None,
                 Body: BlockStatment {
                     Statement: vec![
                         Statement::Let {
                             name: "x".to_string(),
                             value: Expression::InfixExpression {
-                                Token: Token {
-                                    token_type: TokenType::Addition,
-                                    literal: "+".to_string(),
-                                },
-                                Left: Box::new(Expression::Indentifier("a".to_string())),
+                                Token: Token::new(TokenType::Addition, "+".to_string()),
+                                Left: Box::new(Expression::Indentifier(
+                                    "a".to_string(),
+                                    Span::default(),
+                                )),
                                 Op: Op::Add,
-                                Right: Box::new(Some(Expression::Indentifier("b".to_string()))),
+                                Right: Box::new(Some(Expression::Indentifier(
+                                    "b".to_string(),
+                                    Span::default(),
+                                ))),
                             },
+                            span: Span::default(),
                         },
                         Statement::Return {
-                            value: Expression::Indentifier("x".to_string()),
+                            value: Expression::Indentifier("x".to_string(), Span::default()),
+                            span: Span::default(),
                         },
                     ],
                 },
             },
+            span: Span::default(),
         }]);
 
-        p_assert_eq!(program, expected_program);
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program));
     }
 
     #[test]
@@ -452,41 +1392,41 @@ mod tests {
         let mut parser = Parser::new(lexer);
         parser.read();
         parser.read();
-        let program = parser.parse();
+        let program = parser.parse().unwrap();
 
         let expected_program: ast::Program = Vec::from([Statement::StatmentExpression {
             value: Expression::FunctionLiteral {
-                Token: Token {
-                    token_type: TokenType::Fn,
-                    literal: "fn".to_string(),
-                },
-                Parameters: Some(vec![
-                    Expression::Indentifier("a".to_string()),
-                    Expression::Indentifier("b".to_string()),
-                ]),
+                Token: Token::new(TokenType::Fn, "fn".to_string()),
+                Parameters: Some(vec![parameter("a"), parameter("b")]),
                 Body: BlockStatment {
                     Statement: vec![
                         Statement::Let {
                             name: "x".to_string(),
                             value: Expression::InfixExpression {
-                                Token: Token {
-                                    token_type: TokenType::Addition,
-                                    literal: "+".to_string(),
-                                },
-                                Left: Box::new(Expression::Indentifier("a".to_string())),
+                                Token: Token::new(TokenType::Addition, "+".to_string()),
+                                Left: Box::new(Expression::Indentifier(
+                                    "a".to_string(),
+                                    Span::default(),
+                                )),
                                 Op: Op::Add,
-                                Right: Box::new(Some(Expression::Indentifier("b".to_string()))),
+                                Right: Box::new(Some(Expression::Indentifier(
+                                    "b".to_string(),
+                                    Span::default(),
+                                ))),
                             },
+                            span: Span::default(),
                         },
                         Statement::Return {
-                            value: Expression::Indentifier("x".to_string()),
+                            value: Expression::Indentifier("x".to_string(), Span::default()),
+                            span: Span::default(),
                         },
                     ],
                 },
             },
+            span: Span::default(),
         }]);
 
-        p_assert_eq!(program, expected_program);
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program));
     }
 
     #[test]
@@ -495,39 +1435,36 @@ mod tests {
         let mut parser = Parser::new(lexer);
         parser.read();
         parser.read();
-        let program = parser.parse();
+        let program = parser.parse().unwrap();
 
         let expected_program: ast::Program = Vec::from([Statement::StatmentExpression {
             value: Expression::IfExpression {
-                Token: Token {
-                    token_type: TokenType::If,
-                    literal: "if".to_string(),
-                },
+                Token: Token::new(TokenType::If, "if".to_string()),
                 Condition: Box::new(Expression::InfixExpression {
-                    Token: Token {
-                        token_type: TokenType::Greater,
-                        literal: ">".to_string(),
-                    },
-                    Left: Box::new(Expression::Number(2.0)),
+                    Token: Token::new(TokenType::Greater, ">".to_string()),
+                    Left: Box::new(Expression::Integer(2, Span::default())),
                     Op: Op::GreaterThan,
-                    Right: Box::new(Some(Expression::Number(5.0))),
+                    Right: Box::new(Some(Expression::Integer(5, Span::default()))),
                 }),
                 Consequence: BlockStatment {
                     Statement: vec![Statement::Let {
                         name: "x".to_string(),
-                        value: Expression::Number(2.0),
+                        value: Expression::Integer(2, Span::default()),
+                        span: Span::default(),
                     }],
                 },
                 Alternative: Some(BlockStatment {
                     Statement: vec![Statement::Let {
                         name: "x".to_string(),
-                        value: Expression::Number(4.0),
+                        value: Expression::Integer(4, Span::default()),
+                        span: Span::default(),
                     }],
                 }),
             },
+            span: Span::default(),
         }]);
 
-        p_assert_eq!(program, expected_program);
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program));
     }
 
     #[test]
@@ -539,42 +1476,35 @@ mod tests {
         let mut parser = Parser::new(lexer);
         parser.read();
         parser.read();
-        let program = parser.parse();
+        let program = parser.parse().unwrap();
 
         let expected_program: ast::Program = Vec::from([
             Statement::StatmentExpression {
                 value: Expression::InfixExpression {
-                    Token: Token {
-                        token_type: TokenType::Addition,
-                        literal: "+".to_string(),
-                    },
-                    Left: Box::new(Expression::Number(5.0)),
+                    Token: Token::new(TokenType::Addition, "+".to_string()),
+                    Left: Box::new(Expression::Integer(5, Span::default())),
                     Op: Op::Add,
-                    Right: Box::new(Some(Expression::Number(5.0))),
+                    Right: Box::new(Some(Expression::Integer(5, Span::default()))),
                 },
+                span: Span::default(),
             },
             Statement::StatmentExpression {
                 value: Expression::InfixExpression {
-                    Token: Token {
-                        token_type: TokenType::Addition,
-                        literal: "+".to_string(),
-                    },
-                    Left: Box::new(Expression::Indentifier("a".to_string())),
+                    Token: Token::new(TokenType::Addition, "+".to_string()),
+                    Left: Box::new(Expression::Indentifier("a".to_string(), Span::default())),
                     Op: Op::Add,
                     Right: Box::new(Some(Expression::InfixExpression {
-                        Token: Token {
-                            token_type: TokenType::Star,
-                            literal: "*".to_string(),
-                        },
-                        Left: Box::new(Expression::Indentifier("b".to_string())),
+                        Token: Token::new(TokenType::Star, "*".to_string()),
+                        Left: Box::new(Expression::Indentifier("b".to_string(), Span::default())),
                         Op: Op::Multiply,
-                        Right: Box::new(Some(Expression::Number(6.0))),
+                        Right: Box::new(Some(Expression::Integer(6, Span::default()))),
                     })),
                 },
+                span: Span::default(),
             },
         ]);
 
-        p_assert_eq!(program, expected_program)
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program))
     }
 
     #[test]
@@ -583,32 +1513,57 @@ mod tests {
         let mut parser = Parser::new(lexer);
         parser.read();
         parser.read();
-        let program = parser.parse();
+        let program = parser.parse().unwrap();
 
         let expected_program: ast::Program = Vec::from([
             Statement::StatmentExpression {
                 value: Expression::PrefixExpression {
-                    Token: Token {
-                        token_type: TokenType::Minus,
-                        literal: "-".to_string(),
-                    },
+                    Token: Token::new(TokenType::Minus, "-".to_string()),
                     Op: Op::Subtract,
-                    Right: Box::new(Some(Expression::Number(123.0))),
+                    Right: Box::new(Some(Expression::Integer(123, Span::default()))),
                 },
+                span: Span::default(),
             },
             Statement::StatmentExpression {
                 value: Expression::PrefixExpression {
-                    Token: Token {
-                        token_type: TokenType::Bang,
-                        literal: "!".to_string(),
-                    },
+                    Token: Token::new(TokenType::Bang, "!".to_string()),
                     Op: Op::Bang,
-                    Right: Box::new(Some(Expression::Number(124.0))),
+                    Right: Box::new(Some(Expression::Integer(124, Span::default()))),
                 },
+                span: Span::default(),
             },
         ]);
 
-        p_assert_eq!(program, expected_program);
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program));
+    }
+
+    #[test]
+    fn parse_operator_precedence() {
+        let parse = |input: &str| {
+            let mut parser = Parser::new(lexer::Lexer::new(input.to_string()));
+            parser.read();
+            parser.read();
+            parser.parse().unwrap()
+        };
+
+        let test_case = [
+            ("2 ** 3 ** 2", "2 ** (3 ** 2)"),
+            ("-2 ** 2", "-(2 ** 2)"),
+            ("2 * 3 ** 2", "2 * (3 ** 2)"),
+            ("2 ** 3 * 2", "(2 ** 3) * 2"),
+            ("1 + 10 % 4 * 2", "1 + ((10 % 4) * 2)"),
+            ("7 ~/ 2 * 3", "(7 ~/ 2) * 3"),
+            ("1 - 7 ~/ 2", "1 - (7 ~/ 2)"),
+        ];
+
+        for (input, grouped) in test_case {
+            p_assert_eq!(
+                without_spans(&parse(input)),
+                without_spans(&parse(grouped)),
+                "{}",
+                input
+            );
+        }
     }
 
     #[test]
@@ -617,14 +1572,15 @@ mod tests {
         let mut parser = Parser::new(lexer);
         parser.read();
         parser.read();
-        let program = parser.parse();
+        let program = parser.parse().unwrap();
 
         let expected_program: ast::Program = Vec::from([Statement::Let {
             name: "hello".to_string(),
-            value: (Expression::Number(123.0)),
+            value: (Expression::Integer(123, Span::default())),
+            span: Span::default(),
         }]);
 
-        p_assert_eq!(program, expected_program);
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program));
     }
 
     #[test]
@@ -633,14 +1589,15 @@ mod tests {
         let mut parser = Parser::new(lexer);
         parser.read();
         parser.read();
-        let program = parser.parse();
+        let program = parser.parse().unwrap();
 
         let expected_program: ast::Program = Vec::from([Statement::Let {
             name: "hello".to_string(),
-            value: (Expression::String("Hello World".to_string())),
+            value: (Expression::String("Hello World".to_string(), Span::default())),
+            span: Span::default(),
         }]);
 
-        p_assert_eq!(program, expected_program);
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program));
     }
 
     #[test]
@@ -654,20 +1611,162 @@ mod tests {
         let mut parser = Parser::new(lexer);
         parser.read();
         parser.read();
-        let program = parser.parse();
+        let program = parser.parse().unwrap();
 
         let expected_program: ast::Program = Vec::from([
             Statement::Return {
-                value: (Expression::Number(123.0)),
+                value: (Expression::Integer(123, Span::default())),
+                span: Span::default(),
             },
             Statement::Return {
-                value: (Expression::Number(10.0)),
+                value: (Expression::Integer(10, Span::default())),
+                span: Span::default(),
             },
             Statement::Return {
-                value: (Expression::Number(92031203.0)),
+                value: (Expression::Integer(92031203, Span::default())),
+                span: Span::default(),
             },
         ]);
 
-        p_assert_eq!(program, expected_program);
+        p_assert_eq!(without_spans(&program), without_spans(&expected_program));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let test_case = [
+            ("let x 5", vec!["expected `=`, found number `5`"]),
+            ("let = 5", vec!["expected identifier, found `=`"]),
+            ("fn(a b) { a }", vec!["expected `)`, found identifier `b`"]),
+            (
+                "if (True) { 1 ",
+                vec!["unterminated block: `{` is never closed"],
+            ),
+            ("(1 + 2", vec!["expected `)`, found end of file"]),
+            ("add(1, 2", vec!["expected `)`, found end of file"]),
+            ("let x = 5 $ 2", vec!["unexpected character: `$`"]),
+            ("0xfg", vec!["invalid number literal: `0xfg`"]),
+            ("0b102", vec!["invalid number literal: `0b102`"]),
+            (
+                "9223372036854775808",
+                vec!["invalid number literal: `9223372036854775808`"],
+            ),
+            ("{\"a\" 1}", vec!["expected `:`, found number `1`"]),
+            (
+                "{\"a\": 1 \"b\": 2}",
+                vec!["expected `,`, found string `b`"],
+            ),
+            ("1 = 2", vec!["invalid assignment target: `1`"]),
+            ("a + b += 2", vec!["invalid assignment target: `a+b`"]),
+            ("const x = 1 x = 2", vec!["cannot assign to constant: x"]),
+            (
+                "const h = {} let f = fn() { h[\"a\"] += 1 }",
+                vec!["cannot assign to constant: h"],
+            ),
+            (
+                "const x = 1 for i in [1] { x = i }",
+                vec!["cannot assign to constant: x"],
+            ),
+            ("break", vec!["`break` outside of a loop"]),
+            (
+                "while (True) { let f = fn() { continue } }",
+                vec!["`continue` outside of a loop"],
+            ),
+            (
+                "for x in [1] { fn f(a = if (x) { break }) {} }",
+                vec!["`break` outside of a loop"],
+            ),
+            (
+                "for 1 in xs {}",
+                vec!["expected identifier, found number `1`"],
+            ),
+            (
+                "for x of xs {}",
+                vec!["expected `in`, found identifier `of`"],
+            ),
+            ("while True {}", vec!["expected `(`, found boolean `True`"]),
+            ("f()[0] = 2", vec!["invalid assignment target: `f()[0]`"]),
+            ("fn 1() {}", vec!["expected `(`, found number `1`"]),
+            (
+                "fn(a = 1, b) {}",
+                vec!["parameter `b` needs a default value, it follows one that has a default"],
+            ),
+            ("fn(...a, b) {}", vec!["expected `)`, found identifier `b`"]),
+            ("[1,,]", vec!["unexpected token: `,`"]),
+            ("[,]", vec!["unexpected token: `,`"]),
+            ("f(,)", vec!["unexpected token: `,`"]),
+            ("{,}", vec!["unexpected token: `,`"]),
+            ("fn(,) {}", vec!["expected identifier, found `,`"]),
+            ("fn(...a = 1) {}", vec!["expected `)`, found `=`"]),
+            ("fn(a, ...) {}", vec!["expected identifier, found `)`"]),
+            (
+                "f(a: 1, 2)",
+                vec!["positional argument after named arguments: `2`"],
+            ),
+            ("f(a: )", vec!["unexpected token: `)`"]),
+            ("f(1: 2)", vec!["expected `)`, found `:`"]),
+            ("fn f {}", vec!["expected `(`, found `{`"]),
+            (
+                "const x = 1 fn x() { 2 } x",
+                vec!["`x` is declared as both a constant and a function in the same scope"],
+            ),
+            (
+                "fn x() { 2 } const x = 1 x",
+                vec!["`x` is declared as both a constant and a function in the same scope"],
+            ),
+            ("\"${}\"", vec!["unexpected token: `}`"]),
+            ("\"${a b}\"", vec!["expected `}`, found identifier `b`"]),
+            ("\"a\\qb\" 1", vec!["unknown escape sequence: `\\q`"]),
+            ("\"abc", vec!["unterminated string"]),
+            (
+                "let a 1; let b = 2; let = 3; ) let c = 4",
+                vec![
+                    "expected `=`, found number `1`",
+                    "expected identifier, found `=`",
+                    "unexpected token: `)`",
+                ],
+            ),
+            (
+                "let f = fn() { let x 1; x + } let y = 2",
+                vec!["expected `=`, found number `1`", "unexpected token: `}`"],
+            ),
+            (
+                "let y 4 fn f( { 1 }",
+                vec![
+                    "expected `=`, found number `4`",
+                    "expected identifier, found `{`",
+                ],
+            ),
+        ];
+
+        for (input, expected) in test_case {
+            let lexer = lexer::Lexer::new(String::from(input));
+            let mut parser = Parser::new(lexer);
+            parser.read();
+            parser.read();
+            let errors = parser.parse().unwrap_err();
+
+            p_assert_eq!(
+                errors
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<String>>(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn parse_error_spans() {
+        let lexer = lexer::Lexer::new(String::from("let a = 1\nlet b 2"));
+        let mut parser = Parser::new(lexer);
+        parser.read();
+        parser.read();
+        let errors = parser.parse().unwrap_err();
+
+        let span = errors[0].span();
+        p_assert_eq!(
+            (span.line, span.column, span.start, span.end),
+            (2, 7, 16, 17)
+        );
     }
 }
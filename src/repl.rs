@@ -0,0 +1,360 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use crate::ast::{self, Program};
+use crate::diagnostic::{Diagnostic, SourceMap};
+use crate::eval::environment::Env;
+use crate::eval::evaluator;
+use crate::eval::object::Object;
+use crate::lexer::Lexer;
+use crate::parser::{ParseError, Parser};
+use crate::token::Span;
+
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+const HELP: &str = "\
+:env           show the variables defined so far
+:ast <code>    show how <code> is parsed
+:tokens <code> show the tokens of <code>
+:load <file>   evaluate a file in the current environment
+:reset         forget every variable
+:help          show this message
+:quit          leave the repl
+";
+
+/// Interactive read-eval-print loop, the environment is kept between inputs so variables and
+/// functions defined on one line can be used on the next
+pub struct Repl {
+    env: Env,
+    /// Every input and loaded file, errors can point into any of them through the functions
+    /// they defined
+    sources: SourceMap,
+    colour: bool,
+}
+
+impl Repl {
+    pub fn new(colour: bool) -> Self {
+        Self {
+            env: Rc::new(RefCell::new(Default::default())),
+            sources: SourceMap::default(),
+            colour,
+        }
+    }
+
+    /// Reads inputs until the end of `input` or `:quit`, input with unbalanced braces is
+    /// continued on the next line
+    pub fn run(&mut self, input: impl BufRead, output: &mut impl Write) -> io::Result<()> {
+        let mut lines = input.lines();
+        let mut buffer = String::new();
+
+        loop {
+            let prompt = if buffer.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+            write!(output, "{}", prompt)?;
+            output.flush()?;
+
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break,
+            };
+
+            buffer.push_str(&line);
+            buffer.push('\n');
+            if !is_complete(&buffer) {
+                continue;
+            }
+
+            match self.handle(&buffer) {
+                Some(result) => write!(output, "{}", result)?,
+                None => break,
+            }
+            buffer.clear();
+        }
+
+        writeln!(output)
+    }
+
+    /// Handles a single complete input and returns what should be printed, None means the user
+    /// asked to quit
+    pub fn handle(&mut self, input: &str) -> Option<String> {
+        let trimmed = input.trim();
+        if !trimmed.starts_with(':') {
+            return Some(self.eval_source(input, "<repl>"));
+        }
+
+        let (command, argument) = match trimmed.split_once(char::is_whitespace) {
+            Some((command, argument)) => (command, argument.trim()),
+            None => (trimmed, ""),
+        };
+
+        let output = match command {
+            ":quit" | ":q" => return None,
+            ":help" => HELP.to_string(),
+            ":env" => self
+                .env
+                .borrow()
+                .bindings()
+                .iter()
+                .map(|(name, value)| format!("{} = {}\n", name, value))
+                .collect(),
+            ":reset" => {
+                self.env = Rc::new(RefCell::new(Default::default()));
+                String::new()
+            }
+            ":load" => match fs::read_to_string(argument) {
+                Ok(source) => self.eval_source(&source, argument),
+                Err(e) => format!("error: could not read {}: {}\n", argument, e),
+            },
+            ":tokens" => Lexer::with_comments(argument.to_string())
+                .map(|token| match token {
+                    Ok(t) => format!(
+                        "{:?} `{}` {}:{}\n",
+                        t.token_type, t.literal, t.span.line, t.span.column
+                    ),
+                    Err(e) => format!("error: {}\n", e),
+                })
+                .collect(),
+            ":ast" => {
+                let start = self.sources.add("<repl>", argument);
+                match parse(argument, start) {
+                    Ok(program) => program.iter().map(|s| format!("{}\n", s)).collect(),
+                    Err(errors) => self.render_parse_errors(&errors),
+                }
+            }
+            _ => format!("unknown command: {}, try :help\n", command),
+        };
+
+        Some(output)
+    }
+
+    fn eval_source(&mut self, source: &str, file: &str) -> String {
+        let start = self.sources.add(file, source);
+        let program = match parse(source, start) {
+            Ok(program) => program,
+            Err(errors) => return self.render_parse_errors(&errors),
+        };
+
+        match evaluator::eval(ast::Node::Program(program), &self.env) {
+            Ok(Object::Nil) => String::new(),
+            Ok(value) => format!("{}\n", value),
+            Err(e) => Diagnostic::from(&e).render(&self.sources, self.colour),
+        }
+    }
+
+    fn render_parse_errors(&self, errors: &[ParseError]) -> String {
+        errors
+            .iter()
+            .map(|e| Diagnostic::from(e).render(&self.sources, self.colour))
+            .collect()
+    }
+}
+
+/// Parses `source`, which starts at `start` in the sources of the REPL
+fn parse(source: &str, start: Span) -> Result<Program, Vec<ParseError>> {
+    let lexer = Lexer::starting_at(source.to_string(), start);
+    let mut parser = Parser::new(lexer);
+    parser.read();
+    parser.read();
+    parser.parse()
+}
+
+/// Returns false while `source` has more opening than closing brackets, ignoring the contents
+/// of string literals and comments but not the code in their `${...}` interpolations
+fn is_complete(source: &str) -> bool {
+    let mut depth: i64 = 0;
+    let mut quote: Option<char> = None;
+    // Like the lexer's: the quote of the string and the bracket depth for every open `${`
+    let mut interpolations: Vec<(char, i64)> = Vec::new();
+    let mut escaped = false;
+    let mut line_comment = false;
+    let mut block_comments = 0;
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if line_comment {
+            line_comment = c != '\n';
+            continue;
+        }
+        if block_comments > 0 {
+            match (c, chars.peek()) {
+                ('/', Some('*')) => {
+                    chars.next();
+                    block_comments += 1;
+                }
+                ('*', Some('/')) => {
+                    chars.next();
+                    block_comments -= 1;
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (Some(q), '$') if chars.peek() == Some(&'{') => {
+                chars.next();
+                interpolations.push((q, depth));
+                quote = None;
+            }
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '/') if chars.peek() == Some(&'/') => line_comment = true,
+            (None, '/') if chars.peek() == Some(&'*') => {
+                chars.next();
+                block_comments += 1;
+            }
+            // The end of an interpolation, the string continues after it
+            (None, '}')
+                if interpolations
+                    .last()
+                    .is_some_and(|(_, open)| *open == depth) =>
+            {
+                quote = interpolations.pop().map(|(q, _)| q);
+            }
+            (None, '(' | '{' | '[') => depth += 1,
+            (None, ')' | '}' | ']') => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth <= 0 && block_comments == 0 && interpolations.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq as p_assert_eq;
+
+    #[test]
+    fn repl_keeps_environment() {
+        let mut repl = Repl::new(false);
+
+        p_assert_eq!(repl.handle("let a = 5"), Some("".to_string()));
+        p_assert_eq!(
+            repl.handle("let double = fn(x) { x * 2 }"),
+            Some("".to_string())
+        );
+        p_assert_eq!(repl.handle("double(a)"), Some("10\n".to_string()));
+        p_assert_eq!(
+            repl.handle(":env"),
+            Some("a = 5\ndouble = fn(x) { x*2 }\n".to_string())
+        );
+
+        p_assert_eq!(repl.handle(":reset"), Some("".to_string()));
+        p_assert_eq!(repl.handle(":env"), Some("".to_string()));
+        p_assert_eq!(
+            repl.handle("a"),
+            Some(
+                "error: identifier not found: a
+ --> <repl>:4:1
+  |
+4 | a
+  | ^
+"
+                .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn repl_errors_point_into_earlier_inputs() {
+        let mut repl = Repl::new(false);
+        evaluator::set_max_call_depth(3);
+
+        p_assert_eq!(repl.handle("fn f(n) { f(n) }\n"), Some("".to_string()));
+        p_assert_eq!(
+            repl.handle("f(1)\n"),
+            Some(
+                "error: stack overflow: more than 3 nested calls
+ --> <repl>:1:12
+  |
+1 | fn f(n) { f(n) }
+  |            ^
+  = note: in `f` called at <repl>:1:11 (2 times)
+  = note: in `f` called at <repl>:2:1
+"
+                .to_string()
+            )
+        );
+
+        let library = std::env::temp_dir().join("yafika_repl_library.yaf");
+        fs::write(&library, "let one = 1\nfn g() { h }\n").unwrap();
+        let library = library.to_str().unwrap();
+
+        p_assert_eq!(
+            repl.handle(&format!(":load {}", library)),
+            Some("".to_string())
+        );
+        p_assert_eq!(
+            repl.handle("g()\n"),
+            Some(format!(
+                "error: identifier not found: h
+ --> {library}:2:10
+  |
+2 | fn g() {{ h }}
+  |          ^
+  = note: in `g` called at <repl>:3:1
+"
+            ))
+        );
+    }
+
+    #[test]
+    fn repl_meta_commands() {
+        let mut repl = Repl::new(false);
+
+        p_assert_eq!(
+            repl.handle(":tokens let x = 1"),
+            Some("Let `let` 1:1\nIdentifier `x` 1:5\nAssign `=` 1:7\nNumber `1` 1:9\n".to_string())
+        );
+        p_assert_eq!(
+            repl.handle(":ast let x = 1 + 2 * 3"),
+            Some("let x = 1+2*3\n".to_string())
+        );
+        p_assert_eq!(
+            repl.handle(":nope"),
+            Some("unknown command: :nope, try :help\n".to_string())
+        );
+        p_assert_eq!(repl.handle(":quit"), None);
+    }
+
+    #[test]
+    fn repl_multi_line_input() {
+        let mut repl = Repl::new(false);
+        let input = "let add = fn(a, b) {\n  a + b\n}\nadd(1,\n 2)\n:quit\n1\n";
+        let mut output = Vec::new();
+
+        repl.run(input.as_bytes(), &mut output).unwrap();
+
+        p_assert_eq!(
+            String::from_utf8(output).unwrap(),
+            ">> .. .. >> .. 3\n>> \n"
+        );
+    }
+
+    #[test]
+    fn complete_input() {
+        assert!(is_complete("let a = 1"));
+        assert!(is_complete("fn(a) { a }"));
+        assert!(!is_complete("fn(a) {"));
+        assert!(!is_complete("add(1,"));
+        assert!(!is_complete("[1,"));
+        assert!(is_complete("\"{\""));
+        assert!(is_complete("\"\\\"{\""));
+        assert!(is_complete("let a = 1 // don't (\n"));
+        assert!(!is_complete("/* a /* b */"));
+        assert!(is_complete("/* a /* b */ */ [1]"));
+        assert!(is_complete("print(\"${f(\"(\")}\")"));
+        assert!(is_complete("\"${ {\"a\": \"}\"}[\"a\"] }\""));
+        assert!(!is_complete("print(\"${f(\")"));
+        assert!(!is_complete("\"${1"));
+    }
+}
@@ -1,25 +1,47 @@
+use std::fmt::Display;
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(PartialEq, PartialOrd, Clone, Debug)]
 pub enum TokenType {
     // KeyWords
     If,
     Let,
+    Const,
     Fn,
     Else,
     Return,
+    While,
+    For,
+    In,
+    Break,
+    Continue,
 
     // Identifiers + litterals
     Identifier,
     Number,
     String,
+    /// The text of a string before its first `${`
+    TemplateStart,
+    /// The text of a string between the `}` of an interpolation and the next `${`
+    TemplateMiddle,
+    /// The text of a string between the `}` of its last interpolation and the closing quote
+    TemplateEnd,
     Nil,
     Bool,
 
     // Operators
     Assign,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
     Addition,
     Minus,
     Star,
+    StarStar,
     Division,
+    TildeSlash,
+    Percent,
     Bang,
     BangEqual,
     EqualEqual,
@@ -27,23 +49,138 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    And,
+    Or,
 
     // Delimiters
     SemiColon,
+    Colon,
+    Ellipsis,
     Comma,
     LeftParen,
     RightParen,
     RightBrace,
     LeftBrace,
+    LeftBracket,
+    RightBracket,
+
+    /// A `//` or `/* */` comment, only produced by a lexer that keeps comments
+    Comment,
 
     // END OF FILE
     EOF,
 }
 
+impl TokenType {
+    /// Describes a token of this type with the given literal, for use in error messages
+    pub fn describe(&self, literal: &str) -> String {
+        match self {
+            TokenType::Identifier | TokenType::Number | TokenType::String | TokenType::Bool => {
+                format!("{} `{}`", self, literal)
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl Display for TokenType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenType::If => write!(f, "`if`"),
+            TokenType::Let => write!(f, "`let`"),
+            TokenType::Const => write!(f, "`const`"),
+            TokenType::Fn => write!(f, "`fn`"),
+            TokenType::Else => write!(f, "`else`"),
+            TokenType::Return => write!(f, "`return`"),
+            TokenType::While => write!(f, "`while`"),
+            TokenType::For => write!(f, "`for`"),
+            TokenType::In => write!(f, "`in`"),
+            TokenType::Break => write!(f, "`break`"),
+            TokenType::Continue => write!(f, "`continue`"),
+            TokenType::Identifier => write!(f, "identifier"),
+            TokenType::Number => write!(f, "number"),
+            TokenType::String | TokenType::TemplateStart => write!(f, "string"),
+            TokenType::TemplateMiddle | TokenType::TemplateEnd => write!(f, "`}}`"),
+            TokenType::Nil => write!(f, "`nil`"),
+            TokenType::Bool => write!(f, "boolean"),
+            TokenType::Assign => write!(f, "`=`"),
+            TokenType::PlusEqual => write!(f, "`+=`"),
+            TokenType::MinusEqual => write!(f, "`-=`"),
+            TokenType::StarEqual => write!(f, "`*=`"),
+            TokenType::SlashEqual => write!(f, "`/=`"),
+            TokenType::Addition => write!(f, "`+`"),
+            TokenType::Minus => write!(f, "`-`"),
+            TokenType::Star => write!(f, "`*`"),
+            TokenType::Division => write!(f, "`/`"),
+            TokenType::StarStar => write!(f, "`**`"),
+            TokenType::TildeSlash => write!(f, "`~/`"),
+            TokenType::Percent => write!(f, "`%`"),
+            TokenType::Bang => write!(f, "`!`"),
+            TokenType::BangEqual => write!(f, "`!=`"),
+            TokenType::EqualEqual => write!(f, "`==`"),
+            TokenType::Greater => write!(f, "`>`"),
+            TokenType::GreaterEqual => write!(f, "`>=`"),
+            TokenType::Less => write!(f, "`<`"),
+            TokenType::LessEqual => write!(f, "`<=`"),
+            TokenType::And => write!(f, "`&&`"),
+            TokenType::Or => write!(f, "`||`"),
+            TokenType::SemiColon => write!(f, "`;`"),
+            TokenType::Colon => write!(f, "`:`"),
+            TokenType::Ellipsis => write!(f, "`...`"),
+            TokenType::Comma => write!(f, "`,`"),
+            TokenType::LeftParen => write!(f, "`(`"),
+            TokenType::RightParen => write!(f, "`)`"),
+            TokenType::RightBrace => write!(f, "`}}`"),
+            TokenType::LeftBrace => write!(f, "`{{`"),
+            TokenType::LeftBracket => write!(f, "`[`"),
+            TokenType::RightBracket => write!(f, "`]`"),
+            TokenType::Comment => write!(f, "comment"),
+            TokenType::EOF => write!(f, "end of file"),
+        }
+    }
+}
+
+/// Location of a piece of source code.
+///
+/// `start` and `end` are byte offsets into the source (`end` is exclusive), `line` and `column`
+/// are the 1-based position of `start`.
+#[allow(dead_code)]
+#[derive(PartialEq, PartialOrd, Clone, Copy, Debug, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    /// Returns a span covering both `self` and `other`
+    pub fn to(self, other: Span) -> Span {
+        if other.end > self.end {
+            Span {
+                end: other.end,
+                ..self
+            }
+        } else {
+            self
+        }
+    }
+}
+
 #[derive(PartialEq, PartialOrd, Clone, Debug)]
 pub struct Token {
     pub token_type: TokenType,
     pub literal: String,
+    pub span: Span,
 }
 
 impl Token {
@@ -51,6 +188,7 @@ impl Token {
         Self {
             token_type,
             literal,
+            span: Span::default(),
         }
     }
 }